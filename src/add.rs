@@ -1,36 +1,285 @@
-use crate::git_operations::{self, Change};
-use inquire::MultiSelect;
+use crate::{
+    git_operations::{self, Change, Hunk},
+    guard,
+    init::Guard,
+    picker::{self, ChangePicker},
+};
+use crossterm::style::Stylize;
+use git2::Repository;
+use inquire::{MultiSelect, Select};
+use std::fmt;
 
-pub fn stage_files() -> Result<(), String> {
+#[derive(Clone, Copy, PartialEq)]
+enum HunkAction {
+    Take,
+    Skip,
+    PickLines,
+    SkipFile,
+}
+
+impl fmt::Display for HunkAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let label = match self {
+            HunkAction::Take => "Yes",
+            HunkAction::Skip => "No",
+            HunkAction::PickLines => "Pick lines",
+            HunkAction::SkipFile => "Skip the rest of this file",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+pub fn stage_files(
+    guard_config: Guard,
+    pathspecs: &[String],
+    patch: bool,
+    all: bool,
+    modified_only: bool,
+    untracked_only: bool,
+    force: bool,
+) -> Result<(), String> {
     let repo = git_operations::get_repository().map_err(|e| e.to_string())?;
 
-    let (changes, _staged) = git_operations::get_changes(&repo);
+    let (mut changes, _staged) = git_operations::get_changes(&repo);
+
+    if modified_only {
+        changes.retain(|change| !change.is_untracked());
+    }
+    if untracked_only {
+        changes.retain(Change::is_untracked);
+    }
+    if !pathspecs.is_empty() {
+        changes = git_operations::filter_by_pathspecs(&repo, changes, pathspecs)
+            .map_err(|e| format!("Invalid pathspec: {}", e))?;
+        if changes.is_empty() {
+            return Err(format!(
+                "pathspec '{}' did not match any changes",
+                pathspecs.join(" ")
+            ));
+        }
+    }
 
     if changes.is_empty() {
         println!("No untracked or modified files found.");
         return Ok(());
     }
 
+    let prompt_for_files = pathspecs.is_empty() && !all && !modified_only && !untracked_only;
+    if prompt_for_files {
+        picker::ensure_terminal("pass pathspecs or --all to stage without prompting")?;
+    } else if patch {
+        picker::ensure_terminal("--patch needs an interactive terminal")?;
+    }
+
     let mut selected_files = Vec::<Change>::new();
 
-    let selected_unstaged = MultiSelect::new("Select changes to commit:", changes)
-        .prompt()
-        .map_err(|e| format!("An error occurred during selection: {}", e))?;
+    if prompt_for_files {
+        let selected_unstaged = ChangePicker::new("Select changes to commit:", &repo, changes)
+            .prompt()
+            .map_err(|e| format!("An error occurred during selection: {}", e))?;
+
+        if selected_unstaged.is_empty() && selected_files.is_empty() {
+            println!("No files selected.");
+            return Ok(());
+        }
+
+        selected_files.extend(selected_unstaged);
+    } else {
+        selected_files.extend(changes);
+    }
 
-    if selected_unstaged.is_empty() && selected_files.is_empty() {
+    let selected_files = guard::review(&repo, &guard_config, selected_files, force)?;
+    if selected_files.is_empty() {
         println!("No files selected.");
         return Ok(());
     }
 
-    selected_files.extend(selected_unstaged);
-
     let mut index = repo
         .index()
         .map_err(|e| format!("Error accessing index: {}", e))?;
 
-    git_operations::add_files(selected_files, &mut index)
-        .map_err(|e| format!("Failed to add files: {}", e))?;
+    if patch {
+        stage_patches(&repo, selected_files, &mut index)?;
+    } else {
+        git_operations::add_files(selected_files, &mut index)
+            .map_err(|e| format!("Failed to add files: {}", e))?;
+    }
 
     println!("✅ Added files successfuly!");
     Ok(())
 }
+
+/// Lets the user put a whole commit together in one picker: staged files
+/// start out checked, checking another file stages it as it is in the
+/// working tree and unchecking a staged file unstages it.
+pub fn pick_commit_changes(
+    repo: &Repository,
+    guard_config: &Guard,
+    changes: Vec<Change>,
+    staged: Vec<Change>,
+) -> Result<(), String> {
+    // A partially staged file gets two rows: its staged version, which is
+    // kept as it is, and its working tree changes, which stage the whole file.
+    let mut entries = changes;
+    entries.extend(staged.iter().cloned());
+    if entries.is_empty() {
+        return Ok(());
+    }
+
+    let selected = ChangePicker::new("Select changes to commit:", repo, entries)
+        .with_checked(Change::is_staged)
+        .prompt()
+        .map_err(|e| format!("An error occurred during selection: {}", e))?;
+
+    let (kept, to_stage): (Vec<Change>, Vec<Change>) =
+        selected.into_iter().partition(Change::is_staged);
+    let to_unstage: Vec<Change> = staged
+        .into_iter()
+        .filter(|change| {
+            !kept.iter().any(|picked| picked.path == change.path)
+                && !to_stage.iter().any(|picked| picked.path == change.path)
+        })
+        .collect();
+    let to_stage = guard::review(repo, guard_config, to_stage, false)?;
+
+    if !to_stage.is_empty() {
+        let mut index = repo
+            .index()
+            .map_err(|e| format!("Error accessing index: {}", e))?;
+        git_operations::add_files(to_stage, &mut index)
+            .map_err(|e| format!("Failed to add files: {}", e))?;
+    }
+    if !to_unstage.is_empty() {
+        git_operations::reset_files(repo, to_unstage)
+            .map_err(|e| format!("Failed to unstage files: {}", e))?;
+    }
+    Ok(())
+}
+
+fn stage_patches(
+    repo: &Repository,
+    selected_files: Vec<Change>,
+    index: &mut git2::Index,
+) -> Result<(), String> {
+    let mut whole_files = Vec::new();
+
+    for change in selected_files {
+        if !change.supports_hunks() {
+            whole_files.push(change);
+            continue;
+        }
+
+        let hunks = git_operations::get_hunks(repo, &change)
+            .map_err(|e| format!("Failed to diff {}: {}", change.path, e))?;
+
+        // Binary files and pure mode changes have no hunks to pick from.
+        if hunks.is_empty() {
+            whole_files.push(change);
+            continue;
+        }
+
+        let hunks = select_hunks(&change, hunks, "Stage")?;
+        if !hunks.iter().any(Hunk::has_selection) {
+            continue;
+        }
+
+        git_operations::stage_hunks(repo, index, &change, &hunks)
+            .map_err(|e| format!("Failed to stage hunks of {}: {}", change.path, e))?;
+    }
+
+    if !whole_files.is_empty() {
+        git_operations::add_files(whole_files, index)
+            .map_err(|e| format!("Failed to add files: {}", e))?;
+    }
+
+    Ok(())
+}
+
+/// Walks through `hunks` like `git add -p` does, leaving only the lines the
+/// user picked marked as selected.
+pub fn select_hunks(
+    change: &Change,
+    mut hunks: Vec<Hunk>,
+    verb: &str,
+) -> Result<Vec<Hunk>, String> {
+    let total = hunks.len();
+    let mut skip_rest = false;
+
+    for (position, hunk) in hunks.iter_mut().enumerate() {
+        if skip_rest {
+            hunk.set_selected(false);
+            continue;
+        }
+
+        println!(
+            "\n{} ({}/{})",
+            change.to_string().bold(),
+            position + 1,
+            total
+        );
+        print_hunk(hunk);
+
+        let actions = vec![
+            HunkAction::Take,
+            HunkAction::Skip,
+            HunkAction::PickLines,
+            HunkAction::SkipFile,
+        ];
+        let action = Select::new(&format!("{} this hunk?", verb), actions)
+            .prompt()
+            .map_err(|e| format!("An error occurred during selection: {}", e))?;
+
+        match action {
+            HunkAction::Take => {}
+            HunkAction::Skip => hunk.set_selected(false),
+            HunkAction::PickLines => select_lines(hunk, verb)?,
+            HunkAction::SkipFile => {
+                hunk.set_selected(false);
+                skip_rest = true;
+            }
+        }
+    }
+
+    Ok(hunks)
+}
+
+fn select_lines(hunk: &mut Hunk, verb: &str) -> Result<(), String> {
+    let changed: Vec<usize> = hunk
+        .lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| line.is_change())
+        .map(|(idx, _)| idx)
+        .collect();
+    let options: Vec<String> = changed
+        .iter()
+        .map(|&idx| hunk.lines[idx].to_string())
+        .collect();
+
+    let message = format!("Select lines to {}:", verb.to_lowercase());
+    let picked = MultiSelect::new(&message, options)
+        .with_all_selected_by_default()
+        .raw_prompt()
+        .map_err(|e| format!("An error occurred during selection: {}", e))?;
+
+    for &idx in &changed {
+        hunk.lines[idx].selected = false;
+    }
+    for option in picked {
+        hunk.lines[changed[option.index]].selected = true;
+    }
+
+    Ok(())
+}
+
+fn print_hunk(hunk: &Hunk) {
+    println!("{}", hunk.header.as_str().cyan());
+    for line in &hunk.lines {
+        let text = line.to_string();
+        match line.origin {
+            '+' => println!("{}", text.green()),
+            '-' => println!("{}", text.red()),
+            _ => println!("{}", text),
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use crate::git_operations::{self, CommitEntry, SquashAction};
+use git2::Oid;
+use inquire::Confirm;
+
+pub fn run_autosquash(yes: bool) -> Result<(), String> {
+    let repo = git_operations::get_repository().map_err(|e| e.to_string())?;
+
+    if repo.state() != git2::RepositoryState::Clean {
+        return Err("Finish the merge, rebase or cherry-pick in progress first".to_string());
+    }
+    let (changes, staged) = git_operations::get_changes(&repo);
+    if !staged.is_empty() || changes.iter().any(|change| !change.is_untracked()) {
+        return Err("Commit or stash your changes before autosquashing".to_string());
+    }
+
+    let mut history = git_operations::get_linear_history(&repo)
+        .map_err(|e| format!("Failed to read history: {}", e))?;
+    history.reverse();
+
+    let Some(steps) = plan(&history) else {
+        println!("No fixup! or squash! commits to fold.");
+        return Ok(());
+    };
+    let folded = steps
+        .iter()
+        .filter(|(action, _)| *action != SquashAction::Pick)
+        .count();
+
+    if let Some(upstream) = git_operations::get_upstream_containing(&repo, steps[0].1) {
+        println!(
+            "⚠️ Commits being rewritten are already pushed to {}; autosquashing rewrites published history.",
+            upstream
+        );
+        let should_rewrite = yes
+            || Confirm::new("Autosquash anyway?")
+                .with_default(false)
+                .prompt()
+                .map_err(|e| format!("Failed to get confirmation: {}", e))?;
+        if !should_rewrite {
+            println!("❌ Autosquash canceled or failed to get user confirmation.");
+            return Ok(());
+        }
+    }
+
+    git_operations::autosquash(&repo, &steps)
+        .map_err(|e| format!("Autosquash stopped, nothing was changed: {}", e.message()))?;
+
+    println!(
+        "✅ Folded {} commit(s) into their targets. The previous HEAD is saved as ORIG_HEAD.",
+        folded
+    );
+    Ok(())
+}
+
+/// Orders `history` (oldest first) the way `git rebase --autosquash` does,
+/// starting from the oldest commit that has something folded into it.
+fn plan(history: &[CommitEntry]) -> Option<Vec<(SquashAction, Oid)>> {
+    let mut targets: Vec<Option<usize>> = vec![None; history.len()];
+    for (position, entry) in history.iter().enumerate() {
+        let Some((_, subject)) = parse_marker(&entry.summary) else {
+            continue;
+        };
+        let candidates = || {
+            history[..position]
+                .iter()
+                .enumerate()
+                .filter(|(_, candidate)| parse_marker(&candidate.summary).is_none())
+        };
+        // Like git, prefer an exact subject, then a prefix, then a commit id.
+        targets[position] = candidates()
+            .find(|(_, candidate)| candidate.summary == subject)
+            .or_else(|| candidates().find(|(_, candidate)| candidate.summary.starts_with(subject)))
+            .or_else(|| {
+                candidates().find(|(_, candidate)| {
+                    subject.len() >= 4 && candidate.id.to_string().starts_with(subject)
+                })
+            })
+            .map(|(target, _)| target);
+    }
+
+    let base = targets.iter().flatten().min().copied()?;
+
+    let mut steps = Vec::new();
+    for (position, entry) in history.iter().enumerate().skip(base) {
+        if targets[position].is_some() {
+            continue;
+        }
+        steps.push((SquashAction::Pick, entry.id));
+        for (fixup, entry) in history.iter().enumerate() {
+            if targets[fixup] == Some(position) {
+                let (action, _) = parse_marker(&entry.summary)?;
+                steps.push((action, entry.id));
+            }
+        }
+    }
+    Some(steps)
+}
+
+/// Splits `fixup! fixup! subject` into the action and the target subject.
+fn parse_marker(summary: &str) -> Option<(SquashAction, &str)> {
+    let (action, mut subject) = if let Some(rest) = summary.strip_prefix("fixup! ") {
+        (SquashAction::Fixup, rest)
+    } else if let Some(rest) = summary.strip_prefix("squash! ") {
+        (SquashAction::Squash, rest)
+    } else {
+        return None;
+    };
+
+    while let Some(rest) = subject
+        .strip_prefix("fixup! ")
+        .or_else(|| subject.strip_prefix("squash! "))
+    {
+        subject = rest;
+    }
+    Some((action, subject))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(summaries: &[&str]) -> Vec<CommitEntry> {
+        summaries
+            .iter()
+            .enumerate()
+            .map(|(idx, summary)| CommitEntry {
+                id: Oid::from_str(&format!("{:x}", idx + 0xabc0)).unwrap(),
+                summary: summary.to_string(),
+            })
+            .collect()
+    }
+
+    fn steps(history: &[CommitEntry]) -> Option<Vec<String>> {
+        let steps = plan(history)?;
+        Some(
+            steps
+                .into_iter()
+                .map(|(action, id)| {
+                    let action = match action {
+                        SquashAction::Pick => "pick",
+                        SquashAction::Fixup => "fixup",
+                        SquashAction::Squash => "squash",
+                    };
+                    let summary = &history.iter().find(|entry| entry.id == id).unwrap().summary;
+                    format!("{} {}", action, summary)
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn parses_nested_markers() {
+        assert!(matches!(
+            parse_marker("fixup! squash! feat: x"),
+            Some((SquashAction::Fixup, "feat: x"))
+        ));
+        assert!(matches!(
+            parse_marker("squash! feat: x"),
+            Some((SquashAction::Squash, "feat: x"))
+        ));
+        assert!(parse_marker("feat: fixup! x").is_none());
+    }
+
+    #[test]
+    fn moves_fixups_after_their_targets() {
+        let history = history(&[
+            "feat: base",
+            "feat: a",
+            "feat: b",
+            "fixup! feat: a",
+            "squash! feat: b",
+            "fixup! feat: a",
+        ]);
+
+        assert_eq!(
+            steps(&history).unwrap(),
+            [
+                "pick feat: a",
+                "fixup fixup! feat: a",
+                "fixup fixup! feat: a",
+                "pick feat: b",
+                "squash squash! feat: b",
+            ]
+        );
+    }
+
+    #[test]
+    fn matches_subject_prefixes_and_commit_ids() {
+        let history = history(&["feat: add the parser", "fixup! feat: add", "fixup! abc0"]);
+
+        assert_eq!(
+            steps(&history).unwrap(),
+            [
+                "pick feat: add the parser",
+                "fixup fixup! feat: add",
+                "fixup fixup! abc0",
+            ]
+        );
+    }
+
+    #[test]
+    fn nothing_to_do_without_a_matching_target() {
+        assert!(steps(&history(&["feat: a", "fixup! feat: b"])).is_none());
+        assert!(steps(&history(&["feat: a", "feat: b"])).is_none());
+    }
+}
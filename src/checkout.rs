@@ -1,8 +1,17 @@
-use crate::{git_operations, init::Branch};
+use crate::{
+    git_operations,
+    init::{Branch, LengthCheck},
+    limited_text::{self, LimitedText},
+};
 use inquire::{Confirm, Select};
 use regex::Regex;
 
-pub fn run_checkout(branch_config: Branch, create_new: bool) -> Result<(), String> {
+pub fn run_checkout(
+    branch_config: Branch,
+    max_length: usize,
+    length_check: LengthCheck,
+    create_new: bool,
+) -> Result<(), String> {
     if create_new {
         let branch_type = if branch_config.conventional_branches {
             let selected_type = Select::new("Select branch type", branch_config.types)
@@ -13,14 +22,19 @@ pub fn run_checkout(branch_config: Branch, create_new: bool) -> Result<(), Strin
             "".to_string()
         };
 
-        let branch_input = inquire::Text::new("Enter branch name")
+        let re = Regex::new(r" +").unwrap();
+        let full_name =
+            |input: &str| format!("{}{}", branch_type, re.replace_all(input.trim(), "-"));
+
+        let branch_input =
+            LimitedText::new("Enter branch name", "", max_length, length_check, |input| {
+                full_name(input).chars().count()
+            })
             .prompt()
             .map_err(|e| format!("Prompt error: {}", e))?;
 
-        let re = Regex::new(r" +").unwrap();
-        let branch_name = re.replace_all(branch_input.trim(), "-");
-
-        let full_branch = format!("{}{}", branch_type, branch_name);
+        let full_branch = full_name(&branch_input);
+        limited_text::check_length("branch name", &full_branch, max_length, length_check)?;
 
         let should_checkout =
             Confirm::new(&format!("Create and checkout to: \"{}\"?", full_branch))
@@ -1,95 +1,446 @@
-use crate::{git_operations, init::Commit};
-use inquire::{Confirm, Select, Text};
+use crate::{
+    add,
+    git_operations::{self, Change},
+    guard, hooks,
+    init::{Commit, Guard, LengthCheck, TypeRule},
+    limited_text::{self, LimitedText},
+    message::{self, Message},
+    picker,
+    template::{Template, Values},
+};
+use clap::Args;
+use git2::{Pathspec, PathspecFlags, Repository};
+use inquire::{
+    autocompletion::Replacement, validator::Validation, Autocomplete, Confirm, CustomUserError,
+    Editor, MultiSelect, Select, Text,
+};
 use regex::Regex;
+use std::{ffi::OsStr, path::Path};
 
-pub fn run_commit(commit_config: Commit) -> Result<(), String> {
+const EDITOR_HINT: &str = "# Lines starting with '#' are ignored; leave the text empty to skip.";
+
+const CO_AUTHOR_TOKEN: &str = "Co-authored-by";
+
+#[derive(Args)]
+pub struct CommitArgs {
+    #[arg(
+        short = 'a',
+        long = "all",
+        help = "Pick the changes to commit from all files; with -m, stage every tracked change"
+    )]
+    pub all: bool,
+    #[arg(long = "amend", help = "Replace the tip of the current branch")]
+    pub amend: bool,
+    #[arg(long = "type", help = "Commit type")]
+    pub commit_type: Option<String>,
+    #[arg(long = "scope", help = "Commit scope")]
+    pub scope: Option<String>,
+    #[arg(
+        short = 'm',
+        long = "message",
+        help = "Commit subject; other fields without a flag are left empty instead of prompted"
+    )]
+    pub message: Option<String>,
+    #[arg(long = "body", help = "Commit body")]
+    pub body: Option<String>,
+    #[arg(
+        long = "breaking",
+        value_name = "DESCRIPTION",
+        help = "Mark the commit as a breaking change"
+    )]
+    pub breaking: Option<String>,
+    #[arg(
+        long = "ticket",
+        conflicts_with = "no_ticket",
+        help = "Ticket to reference"
+    )]
+    pub ticket: Option<String>,
+    #[arg(long = "no-ticket", help = "Leave out the ticket")]
+    pub no_ticket: bool,
+    #[arg(
+        long = "co-author",
+        value_name = "NAME <EMAIL>",
+        help = "Add a Co-authored-by trailer"
+    )]
+    pub co_authors: Vec<String>,
+    #[arg(
+        long = "trailer",
+        value_name = "TOKEN: VALUE",
+        help = "Add a trailer such as `Refs: #12`"
+    )]
+    pub trailers: Vec<String>,
+    #[arg(
+        short = 'n',
+        long = "no-verify",
+        help = "Skip the pre-commit and commit-msg hooks"
+    )]
+    pub no_verify: bool,
+    #[arg(
+        short = 'y',
+        long = "yes",
+        help = "Commit without asking for confirmation"
+    )]
+    pub yes: bool,
+}
+
+impl CommitArgs {
+    /// A subject on the command line means no prompts at all.
+    fn is_scripted(&self) -> bool {
+        self.message.is_some()
+    }
+}
+
+/// Values the commit prompts start from; empty for a new commit and parsed
+/// from HEAD when amending.
+#[derive(Default)]
+struct Draft {
+    commit_type: Option<String>,
+    scope: String,
+    summary: String,
+    subject: String,
+    ticket: Option<String>,
+    body: String,
+    breaking_change: Option<String>,
+    footers: Vec<String>,
+    co_authors: Vec<String>,
+}
+
+impl Draft {
+    fn parse(text: &str, template: &Template, ticket_patterns: &[Regex]) -> Self {
+        let message = Message::parse(text);
+
+        let (values, summary) =
+            match template.match_header(&message.header, &any_ticket(ticket_patterns)) {
+                Some(values) => {
+                    let without_ticket = Values {
+                        ticket: String::new(),
+                        ..values.clone()
+                    };
+                    // Templates can put the ticket in a footer, so only
+                    // the header line is the summary.
+                    let rendered = template.render(&without_ticket);
+                    let summary = rendered.lines().next().unwrap_or_default().to_string();
+                    (values, summary)
+                }
+                // Written by hand or with another template.
+                None => {
+                    let values = match message::parse_header(&message.header) {
+                        Some(header) => Values {
+                            commit_type: header.commit_type,
+                            scope: header.scope.unwrap_or_default(),
+                            breaking: header.breaking,
+                            subject: header.subject,
+                            ..Values::default()
+                        },
+                        None => Values {
+                            subject: message.header.clone(),
+                            ..Values::default()
+                        },
+                    };
+                    (values, message.header.clone())
+                }
+            };
+
+        let mut ticket = Some(values.ticket).filter(|ticket| !ticket.is_empty());
+        let mut footers = Vec::new();
+        let mut co_authors = Vec::new();
+        for footer in &message.footers {
+            if footer.is_breaking_change() {
+                continue;
+            }
+            if footer.token.eq_ignore_ascii_case(CO_AUTHOR_TOKEN) {
+                co_authors.push(footer.value.clone());
+                continue;
+            }
+            // The template adds a footer like `Refs: {ticket}` back itself.
+            let prefix = format!("{}{}", footer.token, footer.separator);
+            if ticket.is_none()
+                && is_ticket(ticket_patterns, &footer.value)
+                && template.has_ticket_footer(&prefix)
+            {
+                ticket = Some(footer.value.clone());
+                continue;
+            }
+            footers.push(footer.to_string());
+        }
+
+        let breaking_change = message
+            .breaking_change()
+            .map(|footer| footer.value.clone())
+            .or_else(|| values.breaking.then(String::new));
+
+        Self {
+            commit_type: Some(values.commit_type).filter(|commit_type| !commit_type.is_empty()),
+            scope: values.scope,
+            subject: values.subject,
+            summary,
+            ticket,
+            body: message.body,
+            breaking_change,
+            footers,
+            co_authors,
+        }
+    }
+}
+
+pub fn run_commit(
+    commit_config: Commit,
+    guard_config: Guard,
+    args: &CommitArgs,
+) -> Result<(), String> {
     let repo = git_operations::get_repository().map_err(|e| e.to_string())?;
 
+    let conventional_only = args.commit_type.is_some()
+        || args.scope.is_some()
+        || args.body.is_some()
+        || args.breaking.is_some();
+    if conventional_only && !commit_config.conventional_commits {
+        return Err(
+            "--type, --scope, --body and --breaking need conventional commits enabled".to_string(),
+        );
+    }
+    if !args.is_scripted() {
+        picker::ensure_terminal("pass -m/--message to commit without prompting")?;
+    }
+
+    let template = Template::parse(&commit_config.template)
+        .map_err(|e| format!("Invalid commit template: {}", e))?;
+    let ticket_patterns = commit_config
+        .ticket_patterns
+        .iter()
+        .map(|pattern| {
+            Regex::new(pattern).map_err(|e| format!("Invalid ticket pattern '{}': {}", pattern, e))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let operation = git_operations::get_operation_in_progress(&repo);
+    if let (true, Some(operation)) = (args.amend, operation) {
+        return Err(format!(
+            "You are in the middle of a {}; finish it before amending",
+            operation
+        ));
+    }
+    let has_conflicts = repo
+        .index()
+        .map(|index| index.has_conflicts())
+        .map_err(|e| format!("Error accessing index: {}", e))?;
+    if has_conflicts {
+        return Err(
+            "Committing is not possible because you have unmerged files; resolve them with `gq resolve` first"
+                .to_string(),
+        );
+    }
+
+    let (changes, staged) = git_operations::get_changes(&repo);
+    let nothing_staged = staged.is_empty() && !args.amend && operation != Some("merge");
+    if args.all && args.is_scripted() {
+        // Like `git commit -a`, a scripted commit takes every tracked change.
+        let tracked: Vec<Change> = changes
+            .into_iter()
+            .filter(|change| !change.is_untracked())
+            .collect();
+        let tracked = guard::review(&repo, &guard_config, tracked, false)?;
+        let mut index = repo
+            .index()
+            .map_err(|e| format!("Error accessing index: {}", e))?;
+        git_operations::add_files(tracked, &mut index)
+            .map_err(|e| format!("Failed to add files: {}", e))?;
+    } else if args.all || (nothing_staged && !args.is_scripted() && !changes.is_empty()) {
+        let pick_changes = args.all
+            || Confirm::new("No staged files found. Pick changes to commit now?")
+                .with_default(true)
+                .prompt()
+                .map_err(|e| format!("Failed to get confirmation: {}", e))?;
+        if pick_changes {
+            add::pick_commit_changes(&repo, &guard_config, changes, staged)?;
+        }
+    }
     let (_changes, staged) = git_operations::get_changes(&repo);
 
-    if staged.is_empty() {
+    // git prepares the message of a merge, cherry-pick or revert in MERGE_MSG.
+    let merge_message = operation
+        .and_then(|_| repo.message().ok())
+        .map(|text| message::clean(&text));
+
+    let draft = if args.amend {
+        let head = repo
+            .head()
+            .and_then(|head| head.peel_to_commit())
+            .map_err(|_| "Nothing to amend: the current branch has no commits".to_string())?;
+
+        if let Some(upstream) = git_operations::get_pushed_upstream(&repo) {
+            println!(
+                "⚠️ HEAD is already pushed to {}; amending rewrites published history.",
+                upstream
+            );
+            let should_amend = args.yes
+                || Confirm::new("Amend anyway?")
+                    .with_default(false)
+                    .prompt()
+                    .map_err(|e| format!("Failed to get confirmation: {}", e))?;
+            if !should_amend {
+                println!("❌ Amend canceled or failed to get user confirmation.");
+                return Ok(());
+            }
+        }
+
+        Draft::parse(
+            head.message().unwrap_or_default(),
+            &template,
+            &ticket_patterns,
+        )
+    } else if staged.is_empty() && operation != Some("merge") {
         println!("No staged files found.");
         return Ok(());
+    } else if let Some(merge_message) = &merge_message {
+        Draft::parse(merge_message, &template, &ticket_patterns)
+    } else {
+        Draft::default()
+    };
+
+    if !args.no_verify {
+        hooks::run(&repo, hooks::PRE_COMMIT, &[])?;
     }
 
-    let index = repo
+    // Hooks may stage changes of their own, e.g. after formatting.
+    let mut index = repo
         .index()
         .map_err(|e| format!("Error accessing index: {}", e))?;
+    index
+        .read(false)
+        .map_err(|e| format!("Error accessing index: {}", e))?;
 
-    let mut commit_header = if commit_config.conventional_commits {
-        let type_and_scope = get_type_and_scope(commit_config.types)
-            .map_err(|e| format!("An error occurred: {}", e))
-            .map_err(|e| format!("Failed to get confirmation: {}", e))?;
-        type_and_scope
-    } else {
-        String::new()
-    };
+    let mut values = Values::default();
+
+    if commit_config.conventional_commits {
+        (values.commit_type, values.scope) =
+            get_type_and_scope(&repo, &commit_config, &staged, &draft, args)?;
+    }
+
+    values.ticket = get_ticket(&commit_config, &ticket_patterns, &draft, args)?.unwrap_or_default();
 
-    let ticket = if commit_config.ticket_suffix {
-        let re = Regex::new(r"[A-Z]+-[0-9]+").unwrap();
-        let branch = git_operations::get_current_branch().unwrap();
-        re.find(&branch)
-            .map(|regex_match| format!(" ({})", regex_match.as_str()))
-            .unwrap_or_default()
+    let initial_message = if commit_config.conventional_commits {
+        &draft.subject
     } else {
-        "".to_string()
+        &draft.summary
     };
+    // The breaking change marker is only asked for later, so count the
+    // draft's when amending.
+    let header_values = Values {
+        breaking: args.breaking.is_some() || draft.breaking_change.is_some(),
+        ..values.clone()
+    };
+    values.subject = match &args.message {
+        Some(message) => message.clone(),
+        None => prompt_subject(&commit_config, &template, &header_values, initial_message)?,
+    };
+    if values.subject.trim().is_empty() {
+        return Err("Commit message may not be empty".to_string());
+    }
 
-    let user_input = Text::new("Enter commit message:")
-        .prompt()
-        .map_err(|e| format!("An error occurred: {}", e))?;
+    if commit_config.conventional_commits {
+        values.body = match &args.body {
+            Some(body) => body.clone(),
+            None if args.is_scripted() => draft.body.clone(),
+            None => prompt_in_editor(&repo, "Body:", &draft.body)?,
+        };
+        values.body = message::wrap(&values.body, commit_config.body_width);
 
-    let body = if commit_config.conventional_commits {
-        let mut body_text = Text::new("Body:")
-            .prompt()
-            .map_err(|e| format!("An error occurred: {}", e))?;
-        if !body_text.is_empty() {
-            body_text = format!("\n\n{}", body_text);
+        let breaking_change = match &args.breaking {
+            Some(description) => Some(description.clone()),
+            None if args.is_scripted() => draft.breaking_change.clone(),
+            None => prompt_breaking_change(&repo, &draft)?,
         };
-        body_text
+
+        if let Some(breaking_change_desc) = breaking_change {
+            values.breaking = true;
+            if !breaking_change_desc.is_empty() {
+                // The hyphenated token is a valid git trailer, so co-author
+                // and other trailers below it still parse as trailers.
+                let footer = format!("BREAKING-CHANGE: {}", breaking_change_desc);
+                values
+                    .footers
+                    .push(message::wrap_footer(&footer, commit_config.body_width));
+            }
+        }
+
+        // Answering yes adds a `!` the subject prompt could not count yet.
+        let limit = commit_config.max_header_length;
+        if !args.is_scripted()
+            && commit_config.header_length_check == LengthCheck::Block
+            && limit > 0
+            && header_length(&template, &values) > limit
+        {
+            println!(
+                "⚠️ The breaking change marker makes the header too long; shorten the subject."
+            );
+            let subject = values.subject.clone();
+            values.subject = prompt_subject(&commit_config, &template, &values, &subject)?;
+        }
     } else {
-        String::new()
-    };
+        // Without conventional commits only the header is prompted, so
+        // whatever followed it in the amended message is kept as it was.
+        values.body = draft.body.clone();
+        if let Some(breaking_change_desc) = draft.breaking_change.as_ref().filter(|d| !d.is_empty())
+        {
+            values
+                .footers
+                .push(format!("BREAKING-CHANGE: {}", breaking_change_desc));
+        }
+    }
 
-    let footer = if commit_config.conventional_commits {
-        let is_breaking_change = Confirm::new("BREAKING CHANGE?")
-            .with_default(false)
-            .prompt()
-            .map_err(|e| format!("Failed to get confirmation: {}", e))?;
+    values.footers.extend(draft.footers.iter().cloned());
+    for trailer in get_trailers(&repo, &commit_config, &draft, args)? {
+        if !values.footers.contains(&trailer) {
+            values.footers.push(trailer);
+        }
+    }
 
-        let breaking_change = if is_breaking_change {
-            let breaking_change_desc = Text::new("Breaking change description:")
-                .prompt()
-                .map_err(|e| format!("An error occurred: {}", e))?;
-            commit_header.push('!');
-            format!("\n\nBREAKING CHANGE: {}", breaking_change_desc)
-        } else {
-            String::new()
-        };
-        commit_header.push_str(": ");
-        breaking_change
+    let source: &[&str] = if args.amend {
+        &["commit", "HEAD"]
+    } else if merge_message.is_some() && !args.is_scripted() {
+        &["merge"]
     } else {
-        String::new()
+        &["message"]
     };
-
-    let message = format!(
-        "{}{}{}{}{}",
-        commit_header, user_input, ticket, body, footer
-    );
+    let message = template.render(&values);
+    limited_text::check_length(
+        "commit header",
+        message.lines().next().unwrap_or_default(),
+        commit_config.max_header_length,
+        commit_config.header_length_check,
+    )?;
+    let message = hooks::run_message_hooks(&repo, &message, source, !args.no_verify)?;
+    if message.is_empty() {
+        return Err("Aborting commit due to empty commit message".to_string());
+    }
 
     print_in_box(&message);
 
-    let should_commit = Confirm::new("Commit?")
-        .with_default(true)
-        .prompt()
-        .map_err(|e| format!("Failed to get confirmation: {}", e))?;
+    let should_commit = if args.yes {
+        true
+    } else {
+        picker::ensure_terminal("pass --yes to commit without confirming")?;
+        let prompt = if args.amend { "Amend HEAD?" } else { "Commit?" };
+        Confirm::new(prompt)
+            .with_default(true)
+            .prompt()
+            .map_err(|e| format!("Failed to get confirmation: {}", e))?
+    };
 
-    if should_commit {
-        git_operations::commit(repo, index, message)
+    if !should_commit {
+        println!("❌ Commit canceled or failed to get user confirmation.");
+    } else if args.amend {
+        git_operations::amend(&repo, index, message)
+            .map_err(|e| format!("❌ Amend failed: {}", e))?;
+        println!("✅ Amend successful!");
+        hooks::run(&repo, hooks::POST_COMMIT, &[]).ok();
+    } else {
+        git_operations::commit(&repo, index, message)
             .map_err(|e| format!("❌ Commit failed: {}", e))?;
         println!("✅ Commit successful!");
-    } else {
-        println!("❌ Commit canceled or failed to get user confirmation.");
+        // Like git, a failing post-commit hook cannot undo the commit.
+        hooks::run(&repo, hooks::POST_COMMIT, &[]).ok();
     }
 
     Ok(())
@@ -106,18 +457,497 @@ fn print_in_box(message: &str) {
     println!("└{}┘", "─".repeat(max_len + 2));
 }
 
-fn get_type_and_scope(commit_types: Vec<String>) -> Result<String, String> {
-    let selected_type = Select::new("Select commit type", commit_types)
+fn get_ticket(
+    commit_config: &Commit,
+    ticket_patterns: &[Regex],
+    draft: &Draft,
+    args: &CommitArgs,
+) -> Result<Option<String>, String> {
+    if args.no_ticket {
+        if commit_config.require_ticket {
+            return Err("A ticket is required for every commit".to_string());
+        }
+        return Ok(None);
+    }
+
+    if let Some(ticket) = &args.ticket {
+        if !is_ticket(ticket_patterns, ticket) {
+            return Err(format!("'{}' does not match any ticket pattern", ticket));
+        }
+        return Ok(Some(ticket.clone()));
+    }
+
+    // A ticket already in the amended message wins over the branch name.
+    if draft.ticket.is_some() {
+        return Ok(draft.ticket.clone());
+    }
+
+    if commit_config.ticket_suffix {
+        // Detached and unborn HEADs simply have no branch to read from.
+        let branch = git_operations::get_current_branch().unwrap_or_default();
+        if let Some(ticket) = find_ticket(ticket_patterns, &branch) {
+            return Ok(Some(ticket));
+        }
+    }
+
+    if !commit_config.prompt_ticket && !commit_config.require_ticket {
+        return Ok(None);
+    }
+    if args.is_scripted() {
+        if commit_config.require_ticket {
+            return Err("A ticket is required for every commit; pass --ticket".to_string());
+        }
+        return Ok(None);
+    }
+
+    let patterns = ticket_patterns.to_vec();
+    let required = commit_config.require_ticket;
+    let ticket = Text::new("Ticket:")
+        .with_help_message(if required {
+            "required"
+        } else {
+            "leave empty to skip"
+        })
+        .with_validator(move |input: &str| {
+            let input = input.trim();
+            Ok(if input.is_empty() && required {
+                Validation::Invalid("A ticket is required".into())
+            } else if !input.is_empty() && !is_ticket(&patterns, input) {
+                Validation::Invalid("Does not match any ticket pattern".into())
+            } else {
+                Validation::Valid
+            })
+        })
         .prompt()
         .map_err(|e| format!("An error occurred: {}", e))?;
 
-    let mut scope = Text::new("Scope:")
+    Ok(Some(ticket.trim().to_string()).filter(|ticket| !ticket.is_empty()))
+}
+
+fn find_ticket(ticket_patterns: &[Regex], text: &str) -> Option<String> {
+    ticket_patterns
+        .iter()
+        .find_map(|pattern| pattern.find(text))
+        .map(|ticket| ticket.as_str().to_string())
+}
+
+fn is_ticket(ticket_patterns: &[Regex], text: &str) -> bool {
+    ticket_patterns.is_empty()
+        || ticket_patterns.iter().any(|pattern| {
+            pattern
+                .find(text)
+                .is_some_and(|ticket| ticket.len() == text.len())
+        })
+}
+
+/// Combines the ticket patterns into one for matching inside a header.
+fn any_ticket(ticket_patterns: &[Regex]) -> String {
+    ticket_patterns
+        .iter()
+        .map(|pattern| format!("(?:{})", pattern.as_str()))
+        .collect::<Vec<_>>()
+        .join("|")
+}
+
+/// Collects the trailers to add on top of those already in the draft, with
+/// co-authors last as git and GitHub expect.
+fn get_trailers(
+    repo: &Repository,
+    commit_config: &Commit,
+    draft: &Draft,
+    args: &CommitArgs,
+) -> Result<Vec<String>, String> {
+    let mut trailers = Vec::new();
+    for trailer in &args.trailers {
+        let trailer = trailer.trim();
+        if !trailer.contains(": ") || !message::is_footer_line(trailer) {
+            return Err(format!("'{}' is not a trailer like `Refs: #12`", trailer));
+        }
+        trailers.push(trailer.to_string());
+    }
+
+    let co_author_re = Regex::new(r"^[^<>]+ <[^<>\s]+>$").unwrap();
+    let mut co_authors = draft.co_authors.clone();
+    for co_author in &args.co_authors {
+        if !co_author_re.is_match(co_author.trim()) {
+            return Err(format!(
+                "'{}' is not a co-author like `Name <email>`",
+                co_author
+            ));
+        }
+        co_authors.push(co_author.trim().to_string());
+    }
+
+    if commit_config.trailers && !args.is_scripted() {
+        trailers.extend(prompt_trailers(&commit_config.trailer_tokens)?);
+        co_authors = prompt_co_authors(repo, &commit_config.co_authors, co_authors)?;
+    }
+
+    trailers.extend(
+        co_authors
+            .iter()
+            .map(|co_author| format!("{}: {}", CO_AUTHOR_TOKEN, co_author)),
+    );
+    Ok(trailers)
+}
+
+fn prompt_trailers(trailer_tokens: &[String]) -> Result<Vec<String>, String> {
+    const DONE: &str = "No more trailers";
+
+    let mut trailers = Vec::new();
+    if trailer_tokens.is_empty() {
+        return Ok(trailers);
+    }
+
+    let mut options = vec![DONE.to_string()];
+    options.extend(trailer_tokens.iter().cloned());
+    loop {
+        let token = Select::new("Add a trailer?", options.clone())
+            .prompt()
+            .map_err(|e| format!("An error occurred during selection: {}", e))?;
+        if token == DONE {
+            return Ok(trailers);
+        }
+
+        let value = Text::new(&format!("{}:", token))
+            .prompt()
+            .map_err(|e| format!("An error occurred: {}", e))?;
+        if !value.trim().is_empty() {
+            trailers.push(format!("{}: {}", token, value.trim()));
+        }
+    }
+}
+
+fn prompt_co_authors(
+    repo: &Repository,
+    configured: &[String],
+    selected: Vec<String>,
+) -> Result<Vec<String>, String> {
+    let mut candidates = selected.clone();
+    let history = git_operations::get_recent_authors(repo).unwrap_or_default();
+    for co_author in configured.iter().chain(&history) {
+        if !candidates.contains(co_author) {
+            candidates.push(co_author.clone());
+        }
+    }
+
+    if candidates.is_empty() {
+        return Ok(selected);
+    }
+
+    let defaults: Vec<usize> = (0..selected.len()).collect();
+    MultiSelect::new("Co-authors:", candidates)
+        .with_default(&defaults)
+        .prompt()
+        .map_err(|e| format!("An error occurred during selection: {}", e))
+}
+
+/// Asks for the subject while counting down the characters left for the
+/// whole header rendered from `values`.
+fn prompt_subject(
+    commit_config: &Commit,
+    template: &Template,
+    values: &Values,
+    initial: &str,
+) -> Result<String, String> {
+    let subject = LimitedText::new(
+        "Enter commit message:",
+        initial,
+        commit_config.max_header_length,
+        commit_config.header_length_check,
+        |subject| {
+            let values = Values {
+                subject: subject.to_string(),
+                ..values.clone()
+            };
+            header_length(template, &values)
+        },
+    )
+    .prompt()
+    .map_err(|e| format!("An error occurred: {}", e))?;
+
+    if subject.trim().is_empty() {
+        return Err("Commit message may not be empty".to_string());
+    }
+    Ok(subject)
+}
+
+fn header_length(template: &Template, values: &Values) -> usize {
+    let message = template.render(values);
+    message.lines().next().unwrap_or_default().chars().count()
+}
+
+fn prompt_breaking_change(repo: &Repository, draft: &Draft) -> Result<Option<String>, String> {
+    let is_breaking_change = Confirm::new("BREAKING CHANGE?")
+        .with_default(draft.breaking_change.is_some())
+        .prompt()
+        .map_err(|e| format!("Failed to get confirmation: {}", e))?;
+
+    if !is_breaking_change {
+        return Ok(None);
+    }
+
+    let description = draft.breaking_change.as_deref().unwrap_or_default();
+    prompt_in_editor(repo, "Breaking change description:", description).map(Some)
+}
+
+/// Asks for multi-line text in the git editor, dropping comment lines the
+/// same way `git commit` does.
+fn prompt_in_editor(repo: &Repository, prompt: &str, initial: &str) -> Result<String, String> {
+    let editor = git_operations::get_editor(repo);
+    let mut parts = editor.split_whitespace().map(OsStr::new);
+    let command = parts.next().unwrap_or(OsStr::new("vi"));
+    let args: Vec<&OsStr> = parts.collect();
+
+    let text = format!("{}\n\n{}", initial, EDITOR_HINT);
+    let edited = Editor::new(prompt)
+        .with_editor_command(command)
+        .with_args(&args)
+        .with_predefined_text(&text)
         .prompt()
         .map_err(|e| format!("An error occurred: {}", e))?;
 
-    if !scope.is_empty() {
-        scope = format!("({})", scope);
+    Ok(message::clean(&edited))
+}
+
+fn get_type_and_scope(
+    repo: &Repository,
+    commit_config: &Commit,
+    staged: &[Change],
+    draft: &Draft,
+    args: &CommitArgs,
+) -> Result<(String, String), String> {
+    let commit_types = &commit_config.types;
+    let selected_type = match &args.commit_type {
+        Some(commit_type) if commit_types.contains(commit_type) => commit_type.clone(),
+        Some(commit_type) => {
+            return Err(format!(
+                "'{}' is not one of the commit types: {}",
+                commit_type,
+                commit_types.join(", ")
+            ))
+        }
+        None if args.is_scripted() => draft
+            .commit_type
+            .clone()
+            .filter(|commit_type| commit_types.contains(commit_type))
+            .ok_or("pass --type to commit without prompting")?,
+        None => {
+            let suggested_type = match &draft.commit_type {
+                Some(draft_type) => Some(draft_type.clone()),
+                None => suggest_type(&commit_config.type_rules, staged)?,
+            };
+            let starting_cursor = suggested_type
+                .and_then(|suggested| commit_types.iter().position(|t| *t == suggested))
+                .unwrap_or_default();
+
+            Select::new("Select commit type", commit_types.to_vec())
+                .with_starting_cursor(starting_cursor)
+                .prompt()
+                .map_err(|e| format!("An error occurred: {}", e))?
+        }
+    };
+
+    let scope = match &args.scope {
+        Some(scope) => scope.clone(),
+        None if args.is_scripted() => draft.scope.clone(),
+        None => {
+            let completer = ScopeCompleter {
+                scopes: get_scope_suggestions(repo, commit_config, staged),
+            };
+            let scope_config = commit_config.clone();
+            Text::new("Scope:")
+                .with_initial_value(&draft.scope)
+                .with_autocomplete(completer)
+                .with_validator(move |input: &str| {
+                    Ok(match check_scope(&scope_config, input) {
+                        Ok(()) => Validation::Valid,
+                        Err(e) => Validation::Invalid(e.into()),
+                    })
+                })
+                .prompt()
+                .map_err(|e| format!("An error occurred: {}", e))?
+        }
+    };
+    check_scope(commit_config, &scope)?;
+
+    Ok((selected_type, scope))
+}
+
+/// Picks the type of the first rule in `type_rules` that fits the staged
+/// files.
+fn suggest_type(type_rules: &[TypeRule], staged: &[Change]) -> Result<Option<String>, String> {
+    if staged.is_empty() {
+        return Ok(None);
     }
 
-    Ok(format!("{}{}", selected_type, scope))
+    for rule in type_rules {
+        let globs = if rule.globs.is_empty() {
+            None
+        } else {
+            Some(
+                Pathspec::new(&rule.globs)
+                    .map_err(|e| format!("Invalid glob for type '{}': {}", rule.commit_type, e))?,
+            )
+        };
+        // Like the guard globs, `*.md` also matches by file name alone.
+        let matches = |change: &Change| {
+            let Some(globs) = &globs else {
+                return true;
+            };
+            let file_name = change.path.rsplit('/').next().unwrap_or(&change.path);
+            [change.path.as_str(), file_name]
+                .iter()
+                .any(|path| globs.matches_path(Path::new(path), PathspecFlags::DEFAULT))
+        };
+
+        let fits = if rule.added {
+            staged
+                .iter()
+                .any(|change| change.is_added() && matches(change))
+        } else {
+            staged.iter().all(matches)
+        };
+        if fits {
+            return Ok(Some(rule.commit_type.clone()));
+        }
+    }
+    Ok(None)
+}
+
+#[derive(Clone)]
+struct ScopeCompleter {
+    scopes: Vec<String>,
+}
+
+impl Autocomplete for ScopeCompleter {
+    fn get_suggestions(&mut self, input: &str) -> Result<Vec<String>, CustomUserError> {
+        let (done, current) = split_last_scope(input);
+        let current = current.to_lowercase();
+        let entered = split_scopes(done);
+
+        let mut matches: Vec<&String> = self
+            .scopes
+            .iter()
+            .filter(|scope| !entered.contains(&scope.as_str()))
+            .filter(|scope| scope.to_lowercase().contains(&current))
+            .collect();
+        matches.sort_by_key(|scope| !scope.to_lowercase().starts_with(&current));
+
+        Ok(matches
+            .into_iter()
+            .map(|scope| format!("{}{}", done, scope))
+            .collect())
+    }
+
+    fn get_completion(
+        &mut self,
+        input: &str,
+        highlighted_suggestion: Option<String>,
+    ) -> Result<Replacement, CustomUserError> {
+        Ok(match highlighted_suggestion {
+            Some(suggestion) => Some(suggestion),
+            None => self.get_suggestions(input)?.into_iter().next(),
+        })
+    }
+}
+
+/// Splits `api, we` into `api, ` and `we` so only the scope being typed is
+/// completed.
+fn split_last_scope(input: &str) -> (&str, &str) {
+    let start = input.rfind(',').map_or(0, |idx| idx + 1);
+    let current = input[start..].trim_start();
+    input.split_at(input.len() - current.len())
+}
+
+fn split_scopes(scope: &str) -> Vec<&str> {
+    scope
+        .split(',')
+        .map(str::trim)
+        .filter(|scope| !scope.is_empty())
+        .collect()
+}
+
+fn check_scope(commit_config: &Commit, scope: &str) -> Result<(), String> {
+    let scopes = split_scopes(scope);
+    if scopes.is_empty() && commit_config.require_scope {
+        return Err("A scope is required".to_string());
+    }
+
+    let allowed = &commit_config.scopes;
+    if commit_config.strict_scopes && !allowed.is_empty() {
+        if let Some(unknown) = scopes
+            .iter()
+            .find(|scope| !allowed.iter().any(|allowed| allowed == *scope))
+        {
+            return Err(format!(
+                "'{}' is not one of the allowed scopes: {}",
+                unknown,
+                allowed.join(", ")
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Gathers scopes from the staged top-level directories, the config and the
+/// scopes used in recent commits, most frequent first.
+fn get_scope_suggestions(
+    repo: &Repository,
+    commit_config: &Commit,
+    staged: &[Change],
+) -> Vec<String> {
+    let mut candidates: Vec<String> = staged
+        .iter()
+        .filter_map(|change| change.path.split_once('/'))
+        .map(|(directory, _)| directory.to_string())
+        .collect();
+    candidates.extend(commit_config.scopes.iter().cloned());
+
+    let mut history: Vec<(String, usize)> = Vec::new();
+    let summaries = git_operations::get_recent_summaries(repo).unwrap_or_default();
+    for header in summaries
+        .iter()
+        .filter_map(|summary| message::parse_header(summary))
+    {
+        for scope in split_scopes(header.scope.as_deref().unwrap_or_default()) {
+            match history.iter_mut().find(|(known, _)| known == scope) {
+                Some((_, count)) => *count += 1,
+                None => history.push((scope.to_string(), 1)),
+            }
+        }
+    }
+    history.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    candidates.extend(history.into_iter().map(|(scope, _)| scope));
+
+    if commit_config.strict_scopes && !commit_config.scopes.is_empty() {
+        candidates.retain(|scope| commit_config.scopes.contains(scope));
+    }
+
+    // `api` and `API` are the same scope; keep whichever came first.
+    let mut suggestions: Vec<String> = Vec::new();
+    for candidate in candidates {
+        if !suggestions
+            .iter()
+            .any(|known| known.eq_ignore_ascii_case(&candidate))
+        {
+            suggestions.push(candidate);
+        }
+    }
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draft_summary_is_only_the_header_line() {
+        let template = Template::parse("{subject}\n\nRefs: {ticket}").unwrap();
+        let patterns = [Regex::new("[A-Z]+-[0-9]+").unwrap()];
+        let draft = Draft::parse("fix the thing\n\nRefs: ABC-1\n", &template, &patterns);
+
+        assert_eq!(draft.summary, "fix the thing");
+        assert_eq!(draft.ticket.as_deref(), Some("ABC-1"));
+    }
 }
@@ -0,0 +1,80 @@
+use crate::{
+    git_operations::{self, CommitEntry},
+    hooks, picker,
+};
+use inquire::Select;
+
+/// How many recent commits the picker offers as targets.
+const TARGET_LIMIT: usize = 50;
+
+pub fn run_fixup(target: Option<&str>, squash: bool, no_verify: bool) -> Result<(), String> {
+    let repo = git_operations::get_repository().map_err(|e| e.to_string())?;
+    if let Some(operation) = git_operations::get_operation_in_progress(&repo) {
+        return Err(format!("Finish the {} in progress first", operation));
+    }
+
+    let (_changes, staged) = git_operations::get_changes(&repo);
+    if staged.is_empty() {
+        println!("No staged files found.");
+        return Ok(());
+    }
+
+    let target = match target {
+        Some(rev) => {
+            let commit = repo
+                .revparse_single(rev)
+                .and_then(|object| object.peel_to_commit())
+                .map_err(|e| format!("Invalid commit '{}': {}", rev, e))?;
+            CommitEntry {
+                id: commit.id(),
+                summary: commit.summary().unwrap_or_default().to_string(),
+            }
+        }
+        None => {
+            picker::ensure_terminal("pass the commit to fix up")?;
+            let mut history = git_operations::get_linear_history(&repo)
+                .map_err(|e| format!("Failed to read history: {}", e))?;
+            history.truncate(TARGET_LIMIT);
+            if history.is_empty() {
+                return Err("There are no commits to fix up".to_string());
+            }
+
+            let message = if squash {
+                "Select commit to squash into"
+            } else {
+                "Select commit to fix up"
+            };
+            Select::new(message, history)
+                .prompt()
+                .map_err(|e| format!("An error occurred: {}", e))?
+        }
+    };
+
+    let marker = if squash { "squash!" } else { "fixup!" };
+    let message = format!("{} {}", marker, target.summary);
+
+    if !no_verify {
+        hooks::run(&repo, hooks::PRE_COMMIT, &[])?;
+    }
+    let mut index = repo
+        .index()
+        .map_err(|e| format!("Error accessing index: {}", e))?;
+    index
+        .read(false)
+        .map_err(|e| format!("Error accessing index: {}", e))?;
+
+    let message = hooks::run_message_hooks(&repo, &message, &["message"], !no_verify)?;
+    if message.is_empty() {
+        return Err("Aborting commit due to empty commit message".to_string());
+    }
+
+    git_operations::commit(&repo, index, message)
+        .map_err(|e| format!("Failed to commit: {}", e))?;
+    hooks::run(&repo, hooks::POST_COMMIT, &[]).ok();
+
+    println!(
+        "✅ Created a {} commit for {}, run `gq autosquash` to fold it in.",
+        marker, target
+    );
+    Ok(())
+}
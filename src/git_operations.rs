@@ -1,26 +1,208 @@
+use crate::signing;
 use core::fmt;
 use crossterm::style::Stylize;
-use git2::{Repository, Status, StatusOptions};
-use std::{path::Path, process::Command};
+use git2::{
+    Diff, DiffDelta, DiffFindOptions, DiffFormat, DiffOptions, IndexEntry, IndexTime, Oid, Patch,
+    Repository, RepositoryState, StashFlags, Status, StatusOptions,
+};
+use std::{fs, io, path::Path, process::Command};
+
+/// How far back suggestions drawn from history look.
+const HISTORY_LIMIT: usize = 1000;
 
 #[derive(Clone)]
 pub struct Change {
     pub path: String,
+    pub old_path: Option<String>,
     status: git2::Status,
 }
 
+const WORKTREE_STATUS: Status = Status::WT_NEW
+    .union(Status::WT_MODIFIED)
+    .union(Status::WT_DELETED)
+    .union(Status::WT_RENAMED)
+    .union(Status::WT_TYPECHANGE)
+    .union(Status::CONFLICTED);
+
+const INDEX_STATUS: Status = Status::INDEX_NEW
+    .union(Status::INDEX_MODIFIED)
+    .union(Status::INDEX_DELETED)
+    .union(Status::INDEX_RENAMED)
+    .union(Status::INDEX_TYPECHANGE);
+
+impl Change {
+    fn from_delta(
+        delta: Option<DiffDelta>,
+        entry_path: Option<&str>,
+        status: Status,
+    ) -> Option<Self> {
+        let file_path =
+            |file: git2::DiffFile| file.path().and_then(|p| p.to_str()).map(String::from);
+        let path = delta
+            .as_ref()
+            .and_then(|delta| file_path(delta.new_file()))
+            .or_else(|| entry_path.map(String::from))?;
+        let old_path = delta
+            .filter(|_| status.intersects(Status::WT_RENAMED | Status::INDEX_RENAMED))
+            .and_then(|delta| file_path(delta.old_file()));
+        Some(Change {
+            path,
+            old_path,
+            status,
+        })
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self.status {
+            s if s.contains(Status::CONFLICTED) => "conflicted",
+            s if s.intersects(Status::WT_RENAMED | Status::INDEX_RENAMED) => "renamed",
+            s if s.intersects(Status::WT_NEW | Status::INDEX_NEW) => "new",
+            s if s.intersects(Status::WT_MODIFIED | Status::INDEX_MODIFIED) => "modified",
+            s if s.intersects(Status::WT_DELETED | Status::INDEX_DELETED) => "deleted",
+            s if s.intersects(Status::WT_TYPECHANGE | Status::INDEX_TYPECHANGE) => "typechange",
+            _ => "?",
+        }
+    }
+
+    pub fn is_untracked(&self) -> bool {
+        self.status.contains(Status::WT_NEW)
+    }
+
+    /// Whether this is the index side of a file, as opposed to its changes
+    /// in the working tree.
+    pub fn is_staged(&self) -> bool {
+        self.status.intersects(INDEX_STATUS)
+    }
+
+    pub fn is_added(&self) -> bool {
+        self.status.contains(Status::INDEX_NEW)
+    }
+
+    /// Renames, deletions, type changes and conflicts can only be staged as
+    /// a whole file.
+    pub fn supports_hunks(&self) -> bool {
+        self.old_path.is_none()
+            && !self.status.intersects(
+                Status::WT_DELETED
+                    | Status::INDEX_DELETED
+                    | Status::WT_TYPECHANGE
+                    | Status::CONFLICTED,
+            )
+    }
+}
+
 impl fmt::Display for Change {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let status_str = match self.status {
-            s if s.contains(Status::WT_NEW) => "new",
-            s if s.contains(Status::WT_MODIFIED) => "modified",
-            s if s.contains(Status::WT_DELETED) => "deleted",
-            _ => "?",
+        match &self.old_path {
+            Some(old_path) => write!(f, "{}: {} -> {}", self.label(), old_path, self.path),
+            None => write!(f, "{}: {}", self.label(), self.path),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Hunk {
+    pub header: String,
+    old_start: u32,
+    old_lines: u32,
+    pub lines: Vec<HunkLine>,
+}
+
+#[derive(Clone)]
+pub struct HunkLine {
+    pub origin: char,
+    pub content: Vec<u8>,
+    pub selected: bool,
+}
+
+impl Hunk {
+    pub fn set_selected(&mut self, selected: bool) {
+        self.lines
+            .iter_mut()
+            .for_each(|line| line.selected = selected);
+    }
+
+    pub fn has_selection(&self) -> bool {
+        self.lines
+            .iter()
+            .any(|line| line.is_change() && line.selected)
+    }
+}
+
+impl HunkLine {
+    pub fn is_change(&self) -> bool {
+        self.origin == '+' || self.origin == '-'
+    }
+}
+
+impl fmt::Display for HunkLine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let content = String::from_utf8_lossy(&self.content);
+        write!(
+            f,
+            "{}{}",
+            self.origin,
+            content.trim_end_matches(['\n', '\r'])
+        )
+    }
+}
+
+pub struct Conflict {
+    pub path: String,
+    ours: Option<IndexEntry>,
+    theirs: Option<IndexEntry>,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match (&self.ours, &self.theirs) {
+            (Some(_), Some(_)) => "both modified",
+            (None, Some(_)) => "deleted by us",
+            (Some(_), None) => "deleted by them",
+            (None, None) => "?",
         };
-        write!(f, "{}: {}", status_str, self.path)
+        write!(f, "{}: {}", kind, self.path)
     }
 }
 
+#[derive(Clone, Copy)]
+pub enum ConflictSide {
+    Ours,
+    Theirs,
+}
+
+pub struct StashEntry {
+    pub index: usize,
+    pub message: String,
+    pub id: Oid,
+}
+
+impl fmt::Display for StashEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "stash@{{{}}}: {}", self.index, self.message)
+    }
+}
+
+pub struct CommitEntry {
+    pub id: Oid,
+    pub summary: String,
+}
+
+impl fmt::Display for CommitEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let short_id = self.id.to_string()[..7].to_string();
+        write!(f, "{} {}", short_id.yellow(), self.summary)
+    }
+}
+
+/// What autosquash does with a commit it replays.
+#[derive(Clone, Copy, PartialEq)]
+pub enum SquashAction {
+    Pick,
+    Fixup,
+    Squash,
+}
+
 pub struct BranchInfo {
     pub name: String,
     pub is_current: bool,
@@ -89,6 +271,8 @@ pub fn get_changes(repo: &Repository) -> (Vec<Change>, Vec<Change>) {
     let mut status_opts = StatusOptions::new();
     status_opts.include_untracked(true);
     status_opts.recurse_untracked_dirs(true);
+    status_opts.renames_head_to_index(true);
+    status_opts.renames_index_to_workdir(true);
 
     let statuses = match repo.statuses(Some(&mut status_opts)) {
         Ok(statuses) => statuses,
@@ -102,30 +286,85 @@ pub fn get_changes(repo: &Repository) -> (Vec<Change>, Vec<Change>) {
     let mut staged = Vec::new();
 
     for entry in statuses.iter() {
-        if let Some(path) = entry.path() {
-            let path = path.to_string();
-            let status = entry.status();
-            if status.intersects(Status::WT_NEW | Status::WT_MODIFIED | Status::WT_DELETED) {
-                untracked.push(Change {
-                    path: path.clone(),
-                    status,
-                });
-            }
-            if status.intersects(Status::INDEX_NEW | Status::INDEX_MODIFIED | Status::INDEX_DELETED)
-            {
-                staged.push(Change { path, status });
-            }
+        let status = entry.status();
+        let worktree_status = status & WORKTREE_STATUS;
+        let index_status = status & INDEX_STATUS;
+        if !worktree_status.is_empty() {
+            untracked.extend(Change::from_delta(
+                entry.index_to_workdir(),
+                entry.path(),
+                worktree_status,
+            ));
+        }
+        if !index_status.is_empty() {
+            staged.extend(Change::from_delta(
+                entry.head_to_index(),
+                entry.path(),
+                index_status,
+            ));
         }
     }
 
     (untracked, staged)
 }
 
+/// Keeps the changes matching `pathspecs`, which are relative to the
+/// current directory like they are for `git add`.
+pub fn filter_by_pathspecs(
+    repo: &Repository,
+    changes: Vec<Change>,
+    pathspecs: &[String],
+) -> Result<Vec<Change>, git2::Error> {
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| git2::Error::from_str("Repository has no working directory"))?;
+    let current_dir = std::env::current_dir()
+        .and_then(|dir| dir.canonicalize())
+        .map_err(|e| git2::Error::from_str(&e.to_string()))?;
+    let workdir = workdir
+        .canonicalize()
+        .map_err(|e| git2::Error::from_str(&e.to_string()))?;
+    let prefix = current_dir.strip_prefix(&workdir).unwrap_or(Path::new(""));
+
+    let specs: Vec<String> = pathspecs
+        .iter()
+        .map(|spec| {
+            let mut normalized: Vec<String> = Vec::new();
+            for component in prefix.join(spec).components() {
+                match component {
+                    std::path::Component::CurDir => {}
+                    std::path::Component::ParentDir => {
+                        normalized.pop();
+                    }
+                    other => normalized.push(other.as_os_str().to_string_lossy().to_string()),
+                }
+            }
+            if normalized.is_empty() {
+                "*".to_string()
+            } else {
+                normalized.join("/")
+            }
+        })
+        .collect();
+    let pathspec = git2::Pathspec::new(specs)?;
+
+    let matches = |path: &str| pathspec.matches_path(Path::new(path), git2::PathspecFlags::DEFAULT);
+    Ok(changes
+        .into_iter()
+        .filter(|change| matches(&change.path) || change.old_path.as_deref().is_some_and(matches))
+        .collect())
+}
+
 pub fn add_files(selected_files: Vec<Change>, index: &mut git2::Index) -> Result<(), git2::Error> {
     for change in selected_files.iter() {
         let path = Path::new(&change.path);
-        if change.status == Status::WT_DELETED {
+        if let Some(old_path) = &change.old_path {
+            index.remove_path(Path::new(old_path))?;
+        }
+        if change.status.contains(Status::WT_DELETED) {
             index.remove_path(path)?;
+        } else if change.status.contains(Status::CONFLICTED) {
+            add_resolved_path(index, path)?;
         } else {
             index.add_path(path)?;
         }
@@ -135,32 +374,810 @@ pub fn add_files(selected_files: Vec<Change>, index: &mut git2::Index) -> Result
     Ok(())
 }
 
+pub fn reset_files(repo: &Repository, selected_files: Vec<Change>) -> Result<(), git2::Error> {
+    let paths: Vec<&str> = selected_files
+        .iter()
+        .flat_map(|change| std::iter::once(&change.path).chain(&change.old_path))
+        .map(String::as_str)
+        .collect();
+
+    // With nothing committed yet every staged file is new and simply dropped.
+    let head_tree = match repo.head() {
+        Ok(head) => Some(head.peel_to_tree()?),
+        Err(e) if e.code() == git2::ErrorCode::UnbornBranch => None,
+        Err(e) => return Err(e),
+    };
+
+    let mut index = repo.index()?;
+    for path in paths {
+        let head_entry = head_tree
+            .as_ref()
+            .and_then(|tree| tree.get_path(Path::new(path)).ok());
+        match head_entry {
+            Some(entry) => {
+                index.add(&new_index_entry(path, entry.filemode() as u32, entry.id()))?
+            }
+            None => index.remove_path(Path::new(path))?,
+        }
+    }
+
+    index.write()?;
+    Ok(())
+}
+
+/// Diffs a staged change against HEAD and any other change against the index.
+pub fn get_hunks(repo: &Repository, change: &Change) -> Result<Vec<Hunk>, git2::Error> {
+    let mut diff_opts = DiffOptions::new();
+    diff_opts.pathspec(&change.path);
+    if let Some(old_path) = &change.old_path {
+        diff_opts.pathspec(old_path);
+    }
+    diff_opts
+        .disable_pathspec_match(true)
+        .include_untracked(true)
+        .show_untracked_content(true)
+        .recurse_untracked_dirs(true);
+
+    let mut diff = if change.is_staged() {
+        let head_tree = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+        repo.diff_tree_to_index(head_tree.as_ref(), None, Some(&mut diff_opts))?
+    } else {
+        repo.diff_index_to_workdir(None, Some(&mut diff_opts))?
+    };
+    diff.find_similar(Some(
+        DiffFindOptions::new().renames(true).for_untracked(true),
+    ))?;
+
+    let mut hunks = Vec::new();
+    for delta_idx in 0..diff.deltas().len() {
+        let Some(patch) = Patch::from_diff(&diff, delta_idx)? else {
+            continue;
+        };
+        for hunk_idx in 0..patch.num_hunks() {
+            let (hunk, line_count) = patch.hunk(hunk_idx)?;
+            let mut lines = Vec::with_capacity(line_count);
+            for line_idx in 0..line_count {
+                let line = patch.line_in_hunk(hunk_idx, line_idx)?;
+                if matches!(line.origin(), ' ' | '+' | '-') {
+                    lines.push(HunkLine {
+                        origin: line.origin(),
+                        content: line.content().to_vec(),
+                        selected: true,
+                    });
+                }
+            }
+            hunks.push(Hunk {
+                header: String::from_utf8_lossy(hunk.header())
+                    .trim_end()
+                    .to_string(),
+                old_start: hunk.old_start(),
+                old_lines: hunk.old_lines(),
+                lines,
+            });
+        }
+    }
+
+    Ok(hunks)
+}
+
+/// Rebuilds a file from `old` with only the selected lines of `hunks` applied.
+pub fn apply_hunks(old: &[u8], hunks: &[Hunk]) -> Vec<u8> {
+    let old_lines: Vec<&[u8]> = old.split_inclusive(|&b| b == b'\n').collect();
+    let mut result = Vec::with_capacity(old.len());
+    let mut cursor = 0;
+
+    for hunk in hunks {
+        // A zero-length range points at the line *after which* the hunk applies.
+        let start = if hunk.old_lines == 0 {
+            hunk.old_start as usize
+        } else {
+            hunk.old_start.saturating_sub(1) as usize
+        };
+        for line in &old_lines[cursor..start.min(old_lines.len())] {
+            push_line(&mut result, line);
+        }
+        for line in &hunk.lines {
+            let keep = match line.origin {
+                '+' => line.selected,
+                '-' => !line.selected,
+                _ => true,
+            };
+            if keep {
+                push_line(&mut result, &line.content);
+            }
+        }
+        cursor = start + hunk.old_lines as usize;
+    }
+
+    for line in old_lines.iter().skip(cursor) {
+        push_line(&mut result, line);
+    }
+
+    result
+}
+
+/// A line marked `\ No newline at end of file` has no line break of its own,
+/// so one is added when picking lines puts something after it.
+fn push_line(result: &mut Vec<u8>, line: &[u8]) {
+    if result.last().is_some_and(|&byte| byte != b'\n') {
+        result.push(b'\n');
+    }
+    result.extend_from_slice(line);
+}
+
+pub fn stage_hunks(
+    repo: &Repository,
+    index: &mut git2::Index,
+    change: &Change,
+    hunks: &[Hunk],
+) -> Result<(), git2::Error> {
+    let path = Path::new(&change.path);
+    let existing = index.get_path(path, 0);
+
+    let old_content = match &existing {
+        Some(entry) => repo.find_blob(entry.id)?.content().to_vec(),
+        None => Vec::new(),
+    };
+    let content = apply_hunks(&old_content, hunks);
+
+    let mode = match &existing {
+        Some(entry) => entry.mode,
+        None => workdir_mode(repo, path),
+    };
+    let entry = new_index_entry(&change.path, mode, Oid::zero());
+    index.add_frombuffer(&entry, &content)?;
+    index.write()?;
+    Ok(())
+}
+
+/// The index mode for a file git does not track yet, keeping the exec bit.
+fn workdir_mode(repo: &Repository, path: &Path) -> u32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let executable = repo
+            .workdir()
+            .and_then(|workdir| fs::metadata(workdir.join(path)).ok())
+            .is_some_and(|metadata| metadata.permissions().mode() & 0o111 != 0);
+        if executable {
+            return 0o100755;
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = (repo, path);
+    0o100644
+}
+
+pub fn get_conflicts(index: &git2::Index) -> Result<Vec<Conflict>, git2::Error> {
+    let mut conflicts = Vec::new();
+    for conflict in index.conflicts()? {
+        let conflict = conflict?;
+        let path = [&conflict.our, &conflict.their, &conflict.ancestor]
+            .into_iter()
+            .flatten()
+            .map(|entry| String::from_utf8_lossy(&entry.path).to_string())
+            .next()
+            .ok_or_else(|| git2::Error::from_str("Conflict entry without a path"))?;
+        conflicts.push(Conflict {
+            path,
+            ours: conflict.our,
+            theirs: conflict.their,
+        });
+    }
+    Ok(conflicts)
+}
+
+pub fn mark_resolved(index: &mut git2::Index, path: &str) -> Result<(), git2::Error> {
+    add_resolved_path(index, Path::new(path))?;
+    index.write()
+}
+
+fn add_resolved_path(index: &mut git2::Index, path: &Path) -> Result<(), git2::Error> {
+    // A conflict may be resolved by deleting the file, which git2
+    // reports as a missing path when trying to add it.
+    match index.add_path(path) {
+        Err(e) if e.code() == git2::ErrorCode::NotFound => index.remove_path(path),
+        result => result,
+    }
+}
+
+pub fn resolve_conflict(
+    repo: &Repository,
+    index: &mut git2::Index,
+    conflict: &Conflict,
+    side: ConflictSide,
+) -> Result<(), git2::Error> {
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| git2::Error::from_str("Repository has no working directory"))?;
+    let file_path = workdir.join(&conflict.path);
+    let entry = match side {
+        ConflictSide::Ours => &conflict.ours,
+        ConflictSide::Theirs => &conflict.theirs,
+    };
+
+    match entry {
+        Some(entry) => {
+            let blob = repo.find_blob(entry.id)?;
+            if let Some(parent) = file_path.parent() {
+                fs::create_dir_all(parent).map_err(|e| git2::Error::from_str(&e.to_string()))?;
+            }
+            fs::write(&file_path, blob.content())
+                .map_err(|e| git2::Error::from_str(&e.to_string()))?;
+            index.conflict_remove(Path::new(&conflict.path))?;
+            index.add(&new_index_entry(&conflict.path, entry.mode, entry.id))?;
+        }
+        None => {
+            // The chosen side deleted the file, so resolving means deleting it too.
+            if file_path.exists() {
+                fs::remove_file(&file_path).map_err(|e| git2::Error::from_str(&e.to_string()))?;
+            }
+            index.remove_path(Path::new(&conflict.path))?;
+        }
+    }
+
+    index.write()?;
+    Ok(())
+}
+
+/// Resolves the editor the same way git does: `$GIT_EDITOR`, `core.editor`,
+/// `$VISUAL`, `$EDITOR` and finally a platform default.
+pub fn get_editor(repo: &Repository) -> String {
+    let config_editor = repo
+        .config()
+        .and_then(|config| config.get_string("core.editor"))
+        .ok();
+    std::env::var("GIT_EDITOR")
+        .ok()
+        .or(config_editor)
+        .or_else(|| std::env::var("VISUAL").ok())
+        .or_else(|| std::env::var("EDITOR").ok())
+        .filter(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| {
+            if cfg!(windows) {
+                "notepad".to_string()
+            } else {
+                "vi".to_string()
+            }
+        })
+}
+
+pub fn open_in_editor(repo: &Repository, path: &Path) -> Result<(), io::Error> {
+    let editor = get_editor(repo);
+    let mut parts = editor.split_whitespace();
+    let program = parts
+        .next()
+        .ok_or_else(|| io::Error::other("No editor configured"))?;
+
+    let status = Command::new(program).args(parts).arg(path).status()?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "{} exited with {}",
+            program, status
+        )))
+    }
+}
+
+/// Writes the working tree file back with the selected lines of `hunks`
+/// reverted to their indexed version.
+pub fn discard_hunks(
+    repo: &Repository,
+    change: &Change,
+    hunks: &[Hunk],
+) -> Result<(), git2::Error> {
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| git2::Error::from_str("Repository has no working directory"))?;
+    let index = repo.index()?;
+
+    let old_content = match index.get_path(Path::new(&change.path), 0) {
+        Some(entry) => repo.find_blob(entry.id)?.content().to_vec(),
+        None => Vec::new(),
+    };
+    let kept: Vec<Hunk> = hunks
+        .iter()
+        .cloned()
+        .map(|mut hunk| {
+            hunk.lines
+                .iter_mut()
+                .for_each(|line| line.selected = !line.selected);
+            hunk
+        })
+        .collect();
+
+    fs::write(workdir.join(&change.path), apply_hunks(&old_content, &kept))
+        .map_err(|e| git2::Error::from_str(&e.to_string()))
+}
+
+pub fn restore_files(
+    repo: &Repository,
+    selected_files: Vec<Change>,
+    from_head: bool,
+) -> Result<(), git2::Error> {
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| git2::Error::from_str("Repository has no working directory"))?;
+
+    if from_head {
+        reset_files(repo, selected_files.clone())?;
+    }
+
+    let mut index = repo.index()?;
+    let mut checkout = git2::build::CheckoutBuilder::new();
+    checkout.force().disable_pathspec_match(true);
+    let mut has_checkout_paths = false;
+
+    for path in selected_files
+        .iter()
+        .flat_map(|change| std::iter::once(&change.path).chain(&change.old_path))
+    {
+        if index.get_path(Path::new(path), 0).is_some() {
+            checkout.path(path);
+            has_checkout_paths = true;
+        } else {
+            // Files that are not in the index only exist in the working tree.
+            let file_path = workdir.join(path);
+            if file_path.symlink_metadata().is_ok() {
+                fs::remove_file(&file_path).map_err(|e| git2::Error::from_str(&e.to_string()))?;
+            }
+        }
+    }
+
+    // Without any paths the checkout would apply to the whole tree.
+    if has_checkout_paths {
+        repo.checkout_index(Some(&mut index), Some(&mut checkout))?;
+    }
+
+    Ok(())
+}
+
+/// Records the index and the working tree state of `selected_files` as a
+/// dangling stash-shaped commit, so `git stash apply <id>` can bring them back.
+/// `from_head` tells whether the files are restored from HEAD or the index.
+pub fn create_stash_commit(
+    repo: &Repository,
+    selected_files: &[Change],
+    message: &str,
+    from_head: bool,
+) -> Result<Oid, git2::Error> {
+    let workdir = repo
+        .workdir()
+        .ok_or_else(|| git2::Error::from_str("Repository has no working directory"))?;
+    let signature = repo.signature()?;
+
+    let mut repo_index = repo.index()?;
+    let index_tree = repo.find_tree(repo_index.write_tree()?)?;
+
+    // A stash has to be based on a commit. Before the first commit there is
+    // none, so a root commit holding the state the files are restored to
+    // stands in for HEAD, and applying the stash replays just the discarded
+    // changes.
+    let base_commit = match repo.head().ok().and_then(|head| head.peel_to_commit().ok()) {
+        Some(head_commit) => head_commit,
+        None => {
+            let base_tree = if from_head {
+                repo.find_tree(repo.treebuilder(None)?.write()?)?
+            } else {
+                index_tree.clone()
+            };
+            let base_oid = repo.commit(
+                None,
+                &signature,
+                &signature,
+                &format!("base of {}", message),
+                &base_tree,
+                &[],
+            )?;
+            repo.find_commit(base_oid)?
+        }
+    };
+    let head_parents = vec![&base_commit];
+
+    let index_oid = repo.commit(
+        None,
+        &signature,
+        &signature,
+        &format!("index on {}", message),
+        &index_tree,
+        &head_parents,
+    )?;
+    let index_commit = repo.find_commit(index_oid)?;
+
+    let mut backup = git2::Index::new()?;
+    backup.read_tree(&index_tree)?;
+    for path in selected_files
+        .iter()
+        .flat_map(|change| std::iter::once(&change.path).chain(&change.old_path))
+    {
+        let file_path = workdir.join(path);
+        if file_path.is_file() {
+            let mode = repo_index
+                .get_path(Path::new(path), 0)
+                .map_or(0o100644, |entry| entry.mode);
+            let id = repo.blob_path(&file_path)?;
+            backup.add(&new_index_entry(path, mode, id))?;
+        } else {
+            backup.remove_path(Path::new(path))?;
+        }
+    }
+
+    let backup_tree = repo.find_tree(backup.write_tree_to(repo)?)?;
+    let mut parents = head_parents;
+    parents.push(&index_commit);
+    repo.commit(
+        None,
+        &signature,
+        &signature,
+        message,
+        &backup_tree,
+        &parents,
+    )
+}
+
+pub fn get_stashes(repo: &mut Repository) -> Result<Vec<StashEntry>, git2::Error> {
+    let mut stashes = Vec::new();
+    repo.stash_foreach(|index, message, id| {
+        stashes.push(StashEntry {
+            index,
+            message: message.to_string(),
+            id: *id,
+        });
+        true
+    })?;
+    Ok(stashes)
+}
+
+pub fn stash_push(
+    repo: &mut Repository,
+    message: Option<&str>,
+    selected_files: &[Change],
+    include_untracked: bool,
+) -> Result<Oid, git2::Error> {
+    if selected_files.is_empty() {
+        let signature = repo.signature()?;
+        let flags = if include_untracked {
+            StashFlags::INCLUDE_UNTRACKED
+        } else {
+            StashFlags::DEFAULT
+        };
+        return repo.stash_save2(&signature, message, Some(flags));
+    }
+
+    // libgit2's path-limited stash resets the whole working tree, so the
+    // entry is built by hand and only the selected paths are reverted.
+    let head = repo.head()?;
+    let head_commit = head.peel_to_commit()?;
+    let branch = if head.is_branch() {
+        head.shorthand().unwrap_or("(no branch)")
+    } else {
+        "(no branch)"
+    };
+    let full_message = match message {
+        Some(message) => format!("On {}: {}", branch, message),
+        None => format!(
+            "WIP on {}: {} {}",
+            branch,
+            &head_commit.id().to_string()[..7],
+            head_commit.summary().unwrap_or_default()
+        ),
+    };
+
+    let stash_oid = create_stash_commit(repo, selected_files, &full_message, true)?;
+    repo.reference_ensure_log("refs/stash")?;
+    repo.reference("refs/stash", stash_oid, true, &full_message)?;
+    restore_files(repo, selected_files.to_vec(), true)?;
+    Ok(stash_oid)
+}
+
+/// Diffs a stash against the commit it was made on, including the untracked
+/// files it saved in its third parent.
+pub fn get_stash_diffs(repo: &Repository, id: Oid) -> Result<Vec<Diff<'_>>, git2::Error> {
+    let stash = repo.find_commit(id)?;
+    let base_tree = stash.parent(0)?.tree()?;
+    let mut diffs = vec![repo.diff_tree_to_tree(Some(&base_tree), Some(&stash.tree()?), None)?];
+
+    if let Ok(untracked) = stash.parent(2) {
+        diffs.push(repo.diff_tree_to_tree(None, Some(&untracked.tree()?), None)?);
+    }
+
+    Ok(diffs)
+}
+
+/// Flattens a diff into `(origin, text)` lines, marking file headers with
+/// `F` and hunk headers with `@`.
+pub fn get_diff_lines(diff: &Diff) -> Result<Vec<(char, String)>, git2::Error> {
+    let mut lines = Vec::new();
+    diff.print(DiffFormat::Patch, |_, _, line| {
+        let origin = match line.origin() {
+            'F' => 'F',
+            'H' => '@',
+            '+' | '-' | ' ' => line.origin(),
+            _ => ' ',
+        };
+        let content = String::from_utf8_lossy(line.content());
+        for text in content.trim_end_matches(['\n', '\r']).lines() {
+            let text = match origin {
+                '+' | '-' | ' ' => format!("{}{}", origin, text),
+                _ => text.to_string(),
+            };
+            lines.push((origin, text));
+        }
+        true
+    })?;
+    Ok(lines)
+}
+
+/// Builds an index entry without stat data so git rehashes the file
+/// instead of treating the entry as matching the working tree.
+fn new_index_entry(path: &str, mode: u32, id: Oid) -> IndexEntry {
+    IndexEntry {
+        ctime: IndexTime::new(0, 0),
+        mtime: IndexTime::new(0, 0),
+        dev: 0,
+        ino: 0,
+        mode,
+        uid: 0,
+        gid: 0,
+        file_size: 0,
+        id,
+        flags: 0,
+        flags_extended: 0,
+        path: path.as_bytes().to_vec(),
+    }
+}
+
 pub fn commit(
-    repo: git2::Repository,
+    repo: &Repository,
     mut index: git2::Index,
     message: String,
 ) -> Result<(), git2::Error> {
     let signature = repo.signature()?;
     let tree_oid = index.write_tree()?;
     let tree = repo.find_tree(tree_oid)?;
-    let parent_commits: Vec<git2::Commit> = repo
+    let mut parent_commits: Vec<git2::Commit> = repo
         .head()
         .ok()
         .and_then(|head| head.peel_to_commit().ok().map(|c| vec![c]))
         .unwrap_or_default();
+
+    // Finishing a merge records the merged commits as parents, while a
+    // cherry-pick keeps the author of the picked commit, as git does.
+    let state = repo.state();
+    let mut author = signature.clone();
+    match state {
+        RepositoryState::Merge => {
+            // MERGE_HEAD lists one merged commit per line.
+            let merge_heads = fs::read_to_string(repo.path().join("MERGE_HEAD"))
+                .map_err(|e| git2::Error::from_str(&format!("MERGE_HEAD: {}", e)))?;
+            for line in merge_heads.lines().filter(|line| !line.trim().is_empty()) {
+                parent_commits.push(repo.find_commit(Oid::from_str(line.trim())?)?);
+            }
+        }
+        RepositoryState::CherryPick | RepositoryState::CherryPickSequence => {
+            if let Ok(picked) = repo
+                .find_reference("CHERRY_PICK_HEAD")
+                .and_then(|reference| reference.peel_to_commit())
+            {
+                author = picked.author().to_owned();
+            }
+        }
+        _ => {}
+    }
+
     let parent_refs: Vec<&git2::Commit> = parent_commits.iter().collect();
-    repo.commit(
-        Some("HEAD"),
+    write_commit(
+        repo,
+        &author,
         &signature,
+        &message,
+        &tree,
+        &parent_refs,
+        false,
+    )?;
+    finish_operation(repo, state)
+}
+
+/// Names the merge, cherry-pick or revert in progress, if any.
+pub fn get_operation_in_progress(repo: &Repository) -> Option<&'static str> {
+    match repo.state() {
+        RepositoryState::Merge => Some("merge"),
+        RepositoryState::CherryPick | RepositoryState::CherryPickSequence => Some("cherry-pick"),
+        RepositoryState::Revert | RepositoryState::RevertSequence => Some("revert"),
+        _ => None,
+    }
+}
+
+/// Removes what a merge, cherry-pick or revert leaves behind once its commit
+/// is made. A sequence of picks keeps its sequencer state so that
+/// `git cherry-pick --continue` can go on with the next one.
+fn finish_operation(repo: &Repository, state: RepositoryState) -> Result<(), git2::Error> {
+    let leftovers: &[&str] = match state {
+        RepositoryState::Merge | RepositoryState::CherryPick | RepositoryState::Revert => {
+            repo.cleanup_state()?;
+            // Newer versions of git also write AUTO_MERGE, which libgit2 does not know.
+            &["AUTO_MERGE"]
+        }
+        RepositoryState::CherryPickSequence | RepositoryState::RevertSequence => {
+            &["CHERRY_PICK_HEAD", "REVERT_HEAD", "MERGE_MSG", "AUTO_MERGE"]
+        }
+        _ => &[],
+    };
+
+    for file in leftovers {
+        let path = repo.path().join(file);
+        if path.exists() {
+            fs::remove_file(&path)
+                .map_err(|e| git2::Error::from_str(&format!("{}: {}", file, e)))?;
+        }
+    }
+    Ok(())
+}
+
+pub fn amend(
+    repo: &Repository,
+    mut index: git2::Index,
+    message: String,
+) -> Result<(), git2::Error> {
+    let signature = repo.signature()?;
+    let tree_oid = index.write_tree()?;
+    let tree = repo.find_tree(tree_oid)?;
+    let head = repo.head()?.peel_to_commit()?;
+    let parent_commits: Vec<git2::Commit> = head.parents().collect();
+    let parent_refs: Vec<&git2::Commit> = parent_commits.iter().collect();
+    write_commit(
+        repo,
+        &head.author(),
         &signature,
         &message,
         &tree,
         &parent_refs,
+        true,
     )?;
 
     Ok(())
 }
 
+/// Creates a commit and moves HEAD to it.
+fn write_commit(
+    repo: &Repository,
+    author: &git2::Signature,
+    committer: &git2::Signature,
+    message: &str,
+    tree: &git2::Tree,
+    parents: &[&git2::Commit],
+    amend: bool,
+) -> Result<Oid, git2::Error> {
+    let oid = create_commit(repo, author, committer, message, tree, parents)?;
+
+    let action = if amend {
+        "commit (amend)"
+    } else if parents.is_empty() {
+        "commit (initial)"
+    } else if parents.len() > 1 {
+        "commit (merge)"
+    } else {
+        "commit"
+    };
+    let summary = message.lines().next().unwrap_or_default();
+    move_head(repo, oid, &format!("{}: {}", action, summary))?;
+    Ok(oid)
+}
+
+/// Writes a commit without touching any reference, signing it first when
+/// the repository config asks for signed commits.
+fn create_commit(
+    repo: &Repository,
+    author: &git2::Signature,
+    committer: &git2::Signature,
+    message: &str,
+    tree: &git2::Tree,
+    parents: &[&git2::Commit],
+) -> Result<Oid, git2::Error> {
+    let config = repo.config()?.snapshot()?;
+    let buffer = repo.commit_create_buffer(author, committer, message, tree, parents)?;
+    let content = buffer
+        .as_str()
+        .ok_or_else(|| git2::Error::from_str("Commit buffer is not valid UTF-8"))?;
+
+    match signing::sign_commit(&config, content, committer)? {
+        Some(signature) => repo.commit_signed(content, &signature, None),
+        None => repo.commit(None, author, committer, message, tree, parents),
+    }
+}
+
+/// Points the current branch, or a detached HEAD, at `oid`.
+fn move_head(repo: &Repository, oid: Oid, log_message: &str) -> Result<(), git2::Error> {
+    let head = repo.find_reference("HEAD")?;
+    match head.symbolic_target() {
+        Some(branch) => {
+            repo.reference(branch, oid, true, log_message)?;
+        }
+        None => repo.set_head_detached(oid)?,
+    }
+    Ok(())
+}
+
+/// Returns the upstream of the current branch if it already contains HEAD.
+pub fn get_pushed_upstream(repo: &Repository) -> Option<String> {
+    let head_oid = repo.head().ok()?.target()?;
+    get_upstream_containing(repo, head_oid)
+}
+
+/// Returns the upstream of the current branch if it already contains `oid`.
+pub fn get_upstream_containing(repo: &Repository, oid: Oid) -> Option<String> {
+    let head = repo.head().ok()?;
+    if !head.is_branch() {
+        return None;
+    }
+
+    let upstream = git2::Branch::wrap(head).upstream().ok()?;
+    let upstream_oid = upstream.get().target()?;
+    let contains =
+        upstream_oid == oid || repo.graph_descendant_of(upstream_oid, oid).unwrap_or(false);
+
+    contains.then(|| {
+        upstream
+            .name()
+            .ok()
+            .flatten()
+            .unwrap_or("upstream")
+            .to_string()
+    })
+}
+
+/// Returns the summary lines of recent commits, newest first.
+pub fn get_recent_summaries(repo: &Repository) -> Result<Vec<String>, git2::Error> {
+    let mut revwalk = repo.revwalk()?;
+    if revwalk.push_head().is_err() {
+        return Ok(Vec::new());
+    }
+
+    revwalk
+        .take(HISTORY_LIMIT)
+        .map(|oid| {
+            let commit = repo.find_commit(oid?)?;
+            Ok(commit.summary().unwrap_or_default().to_string())
+        })
+        .collect()
+}
+
+/// Lists the authors of recent commits as `Name <email>`, most active first,
+/// leaving out the current user.
+pub fn get_recent_authors(repo: &Repository) -> Result<Vec<String>, git2::Error> {
+    let own_email = repo
+        .signature()
+        .ok()
+        .and_then(|signature| signature.email().map(str::to_string));
+
+    let mut revwalk = repo.revwalk()?;
+    if revwalk.push_head().is_err() {
+        return Ok(Vec::new());
+    }
+
+    let mut authors: Vec<(String, usize)> = Vec::new();
+    for oid in revwalk.take(HISTORY_LIMIT) {
+        let commit = repo.find_commit(oid?)?;
+        let author = commit.author();
+        let (Some(name), Some(email)) = (author.name(), author.email()) else {
+            continue;
+        };
+        if own_email.as_deref() == Some(email) {
+            continue;
+        }
+        let author = format!("{} <{}>", name, email);
+        match authors.iter_mut().find(|(known, _)| *known == author) {
+            Some((_, count)) => *count += 1,
+            None => authors.push((author, 1)),
+        }
+    }
+
+    authors.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    Ok(authors.into_iter().map(|(author, _)| author).collect())
+}
+
 pub fn checkout_branch(branch: &str) -> Result<(), git2::Error> {
     let repo = get_repository()?;
 
@@ -183,10 +1200,12 @@ pub fn checkout_branch(branch: &str) -> Result<(), git2::Error> {
 pub fn get_current_branch() -> Result<String, git2::Error> {
     let repo = get_repository()?;
 
-    let head = repo.head()?;
-    head.shorthand()
+    // Read HEAD itself so that unborn branches still have a name.
+    let head = repo.find_reference("HEAD")?;
+    head.symbolic_target()
+        .and_then(|target| target.strip_prefix("refs/heads/"))
         .map(|s| s.to_string())
-        .ok_or_else(|| git2::Error::from_str("Failed to get branch name"))
+        .ok_or_else(|| git2::Error::from_str("HEAD is detached"))
 }
 
 pub fn create_and_checkout_branch(branch_name: &str) -> Result<(), git2::Error> {
@@ -209,3 +1228,263 @@ pub fn create_and_checkout_branch(branch_name: &str) -> Result<(), git2::Error>
 
     Ok(())
 }
+
+/// Lists the commits reachable from HEAD by first parents, newest first,
+/// stopping after the first merge since history past it cannot be replayed
+/// as a straight line.
+pub fn get_linear_history(repo: &Repository) -> Result<Vec<CommitEntry>, git2::Error> {
+    let mut history = Vec::new();
+    let Ok(head) = repo.head().and_then(|head| head.peel_to_commit()) else {
+        return Ok(history);
+    };
+
+    let mut next = Some(head);
+    while let Some(commit) = next.take() {
+        if commit.parent_count() > 1 || history.len() == HISTORY_LIMIT {
+            break;
+        }
+        history.push(CommitEntry {
+            id: commit.id(),
+            summary: commit.summary().unwrap_or_default().to_string(),
+        });
+        next = commit.parent(0).ok();
+    }
+    Ok(history)
+}
+
+/// Replays `steps` in memory on top of the parent of the first one, folding
+/// fixup and squash commits into the commit before them, and moves HEAD to
+/// the result. Fails without changing anything when a step conflicts.
+pub fn autosquash(repo: &Repository, steps: &[(SquashAction, Oid)]) -> Result<Oid, git2::Error> {
+    let committer = repo.signature()?;
+    let old_head = repo.head()?.peel_to_commit()?;
+    let mut tip: Option<git2::Commit> = None;
+
+    for &(action, oid) in steps {
+        let commit = repo.find_commit(oid)?;
+        let tip_ids: Vec<Oid> = tip.iter().map(|tip| tip.id()).collect();
+
+        // Commits before the first fixup stay exactly as they are.
+        if action == SquashAction::Pick
+            && (tip.is_none() || commit.parent_ids().eq(tip_ids.iter().copied()))
+        {
+            tip = Some(commit);
+            continue;
+        }
+        let Some(onto) = tip.take() else {
+            return Err(git2::Error::from_str(
+                "Nothing to fold the first commit into",
+            ));
+        };
+
+        let mut index = repo.cherrypick_commit(&commit, &onto, 0, None)?;
+        if index.has_conflicts() {
+            let paths: Vec<String> = index
+                .conflicts()?
+                .filter_map(|conflict| conflict.ok())
+                .filter_map(|conflict| conflict.our.or(conflict.their).or(conflict.ancestor))
+                .map(|entry| String::from_utf8_lossy(&entry.path).into_owned())
+                .collect();
+            return Err(git2::Error::from_str(&format!(
+                "{} conflicts in {}",
+                commit.summary().unwrap_or_default(),
+                paths.join(", ")
+            )));
+        }
+        let tree = repo.find_tree(index.write_tree_to(repo)?)?;
+
+        let new_oid = match action {
+            SquashAction::Pick => create_commit(
+                repo,
+                &commit.author(),
+                &committer,
+                commit.message().unwrap_or_default(),
+                &tree,
+                &[&onto],
+            )?,
+            SquashAction::Fixup | SquashAction::Squash => {
+                let mut message = onto.message().unwrap_or_default().trim_end().to_string();
+                let extra = commit.body().unwrap_or_default().trim();
+                if action == SquashAction::Squash && !extra.is_empty() {
+                    message.push_str("\n\n");
+                    message.push_str(extra);
+                }
+                message.push('\n');
+
+                let parents: Vec<git2::Commit> = onto.parents().collect();
+                let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+                create_commit(
+                    repo,
+                    &onto.author(),
+                    &committer,
+                    &message,
+                    &tree,
+                    &parent_refs,
+                )?
+            }
+        };
+        tip = Some(repo.find_commit(new_oid)?);
+    }
+
+    let new_head = tip.ok_or_else(|| git2::Error::from_str("Nothing to replay"))?;
+    if new_head.tree_id() != old_head.tree_id() {
+        let mut checkout = git2::build::CheckoutBuilder::new();
+        checkout.safe();
+        repo.checkout_tree(new_head.as_object(), Some(&mut checkout))?;
+    }
+    repo.reference("ORIG_HEAD", old_head.id(), true, "autosquash")?;
+    move_head(repo, new_head.id(), "autosquash: fold fixup commits")?;
+    Ok(new_head.id())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signing;
+
+    fn hunk(old_start: u32, old_lines: u32, lines: &[(char, &str, bool)]) -> Hunk {
+        Hunk {
+            header: String::new(),
+            old_start,
+            old_lines,
+            lines: lines
+                .iter()
+                .map(|&(origin, content, selected)| HunkLine {
+                    origin,
+                    content: content.as_bytes().to_vec(),
+                    selected,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn apply_hunks_takes_only_selected_lines() {
+        let old = b"one\ntwo\nthree\n";
+        let hunks = [hunk(
+            2,
+            1,
+            &[
+                ('-', "two\n", true),
+                ('+', "2\n", true),
+                ('+', "2.5\n", false),
+            ],
+        )];
+
+        assert_eq!(apply_hunks(old, &hunks), b"one\n2\nthree\n");
+    }
+
+    #[test]
+    fn apply_hunks_keeps_unselected_deletions() {
+        let old = b"one\ntwo\nthree\n";
+        let hunks = [hunk(2, 1, &[('-', "two\n", false), ('+', "2\n", true)])];
+
+        assert_eq!(apply_hunks(old, &hunks), b"one\ntwo\n2\nthree\n");
+    }
+
+    #[test]
+    fn apply_hunks_inserts_after_zero_length_range() {
+        let old = b"one\ntwo\n";
+        let hunks = [hunk(1, 0, &[('+', "1.5\n", true)])];
+
+        assert_eq!(apply_hunks(old, &hunks), b"one\n1.5\ntwo\n");
+    }
+
+    #[test]
+    fn apply_hunks_applies_several_hunks_in_order() {
+        let old = b"a\nb\nc\nd\ne\n";
+        let hunks = [
+            hunk(1, 1, &[('-', "a\n", true), ('+', "A\n", true)]),
+            hunk(5, 1, &[('-', "e\n", true), ('+', "E\n", false)]),
+        ];
+
+        assert_eq!(apply_hunks(old, &hunks), b"A\nb\nc\nd\n");
+    }
+
+    #[test]
+    fn apply_hunks_does_not_join_lines_without_newline_at_eof() {
+        // `\ No newline at end of file` on the old side: appending a line
+        // while keeping the old last line must not merge the two.
+        let old = b"x\na";
+        let hunks = [hunk(
+            2,
+            1,
+            &[('-', "a", false), ('+', "a\n", false), ('+', "b", true)],
+        )];
+
+        assert_eq!(apply_hunks(old, &hunks), b"x\na\nb");
+    }
+
+    #[test]
+    fn apply_hunks_keeps_missing_newline_when_nothing_follows() {
+        let old = b"x\na";
+        let hunks = [hunk(2, 1, &[('-', "a", true), ('+', "b", true)])];
+
+        assert_eq!(apply_hunks(old, &hunks), b"x\nb");
+    }
+
+    #[test]
+    fn write_commit_signs_when_gpgsign_is_set() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path().join("repo")).unwrap();
+        let key = signing::tests::generate_ssh_key(dir.path());
+
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        config.set_bool("commit.gpgsign", true).unwrap();
+        config.set_str("gpg.format", "ssh").unwrap();
+        config
+            .set_str("user.signingkey", &key.to_string_lossy())
+            .unwrap();
+
+        fs::write(dir.path().join("repo/file.txt"), "content\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("file.txt")).unwrap();
+        index.write().unwrap();
+        commit(&repo, index, "feat: signed\n".to_string()).unwrap();
+
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        let gpgsig = head.header_field_bytes("gpgsig").unwrap();
+        assert!(String::from_utf8_lossy(&gpgsig).contains("BEGIN SSH SIGNATURE"));
+        assert_eq!(head.message(), Some("feat: signed\n"));
+        assert!(repo.extract_signature(&head.id(), None).is_ok());
+    }
+
+    fn restore_and_apply_backup(from_head: bool) -> String {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+
+        let file = dir.path().join("file.txt");
+        fs::write(&file, "staged\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("file.txt")).unwrap();
+        index.write().unwrap();
+        fs::write(&file, "edited\n").unwrap();
+
+        let (mut changes, staged) = get_changes(&repo);
+        if from_head {
+            changes = staged;
+        }
+        let backup = create_stash_commit(&repo, &changes, "backup", from_head).unwrap();
+        restore_files(&repo, changes, from_head).unwrap();
+
+        let status = std::process::Command::new("git")
+            .args(["stash", "apply", &backup.to_string()])
+            .current_dir(dir.path())
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success());
+        fs::read_to_string(&file).unwrap()
+    }
+
+    #[test]
+    fn restore_backups_apply_before_the_first_commit() {
+        assert_eq!(restore_and_apply_backup(false), "edited\n");
+        assert_eq!(restore_and_apply_backup(true), "edited\n");
+    }
+}
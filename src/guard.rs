@@ -0,0 +1,149 @@
+use crate::{git_operations::Change, init::Guard, picker};
+use crossterm::style::Stylize;
+use git2::{Pathspec, PathspecFlags, Repository};
+use inquire::MultiSelect;
+use regex::Regex;
+use std::{fs, path::Path};
+
+// Same heuristic git uses: a NUL byte early on means binary content.
+const BINARY_SNIFF_LEN: usize = 8000;
+
+struct Finding {
+    change: Change,
+    reasons: Vec<String>,
+}
+
+struct Rules<'a> {
+    config: &'a Guard,
+    globs: Option<Pathspec>,
+    patterns: Vec<Regex>,
+}
+
+/// Warns about selected files that look like secrets or do not belong in
+/// the repository, and lets the user decide which of them to stage anyway.
+pub fn review(
+    repo: &Repository,
+    guard_config: &Guard,
+    selected_files: Vec<Change>,
+    force: bool,
+) -> Result<Vec<Change>, String> {
+    if !guard_config.enabled {
+        return Ok(selected_files);
+    }
+
+    let workdir = repo
+        .workdir()
+        .ok_or("Repository has no working directory")?;
+    let rules = Rules::new(guard_config)?;
+
+    let mut clean = Vec::new();
+    let mut flagged = Vec::new();
+    for change in selected_files {
+        let reasons = rules.scan(&workdir.join(&change.path), &change.path);
+        if reasons.is_empty() {
+            clean.push(change);
+        } else {
+            flagged.push(Finding { change, reasons });
+        }
+    }
+
+    if flagged.is_empty() {
+        return Ok(clean);
+    }
+
+    println!("⚠️ Some selected files look risky to stage:");
+    for finding in &flagged {
+        println!(
+            "  {} ({})",
+            finding.change.path.as_str().yellow(),
+            finding.reasons.join(", ")
+        );
+    }
+
+    let flagged: Vec<Change> = flagged.into_iter().map(|finding| finding.change).collect();
+    if force {
+        clean.extend(flagged);
+        return Ok(clean);
+    }
+
+    picker::ensure_terminal("use `gq add --force` to stage flagged files anyway")?;
+    let approved = MultiSelect::new("Stage flagged files anyway?", flagged)
+        .prompt()
+        .map_err(|e| format!("An error occurred during selection: {}", e))?;
+
+    clean.extend(approved);
+    Ok(clean)
+}
+
+impl<'a> Rules<'a> {
+    fn new(config: &'a Guard) -> Result<Self, String> {
+        // An empty pathspec matches everything, so it must not be built.
+        let globs = if config.blocked_globs.is_empty() {
+            None
+        } else {
+            Some(
+                Pathspec::new(&config.blocked_globs)
+                    .map_err(|e| format!("Invalid blocked glob: {}", e))?,
+            )
+        };
+        let patterns = config
+            .secret_patterns
+            .iter()
+            .map(|pattern| {
+                Regex::new(pattern)
+                    .map_err(|e| format!("Invalid secret pattern '{}': {}", pattern, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            config,
+            globs,
+            patterns,
+        })
+    }
+
+    fn scan(&self, file_path: &Path, repo_path: &str) -> Vec<String> {
+        let mut reasons = Vec::new();
+
+        // Deletions never add anything sensitive to the repository.
+        let Ok(metadata) = fs::metadata(file_path) else {
+            return reasons;
+        };
+        if !metadata.is_file() {
+            return reasons;
+        }
+
+        let file_name = repo_path.rsplit('/').next().unwrap_or(repo_path);
+        if let Some(globs) = &self.globs {
+            let matches = |path: &str| globs.matches_path(Path::new(path), PathspecFlags::DEFAULT);
+            if matches(repo_path) || matches(file_name) {
+                reasons.push("matches a blocked file pattern".to_string());
+            }
+        }
+
+        let max_size_kb = self.config.max_file_size_kb;
+        if max_size_kb > 0 && metadata.len() > max_size_kb * 1024 {
+            reasons.push(format!("larger than {} KB", max_size_kb));
+            return reasons;
+        }
+
+        let Ok(content) = fs::read(file_path) else {
+            return reasons;
+        };
+
+        if content.iter().take(BINARY_SNIFF_LEN).any(|&byte| byte == 0) {
+            if self.config.flag_binary {
+                reasons.push("binary file".to_string());
+            }
+            return reasons;
+        }
+
+        let text = String::from_utf8_lossy(&content);
+        if let Some(found) = self.patterns.iter().find_map(|pattern| pattern.find(&text)) {
+            let line = text[..found.start()].matches('\n').count() + 1;
+            reasons.push(format!("possible secret on line {}", line));
+        }
+
+        reasons
+    }
+}
@@ -0,0 +1,125 @@
+use crate::message;
+use git2::Repository;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+pub const PRE_COMMIT: &str = "pre-commit";
+pub const PREPARE_COMMIT_MSG: &str = "prepare-commit-msg";
+pub const COMMIT_MSG: &str = "commit-msg";
+pub const POST_COMMIT: &str = "post-commit";
+
+/// Runs `name` from `core.hooksPath` or `.git/hooks` if it exists, failing
+/// when the hook exits with a non-zero status.
+pub fn run(repo: &Repository, name: &str, args: &[&str]) -> Result<(), String> {
+    let Some(path) = find_hook(repo, name) else {
+        return Ok(());
+    };
+
+    // Windows cannot execute hook scripts directly, git runs them with sh.
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("sh");
+        command.arg(&path);
+        command
+    } else {
+        Command::new(&path)
+    };
+
+    let status = command
+        .args(args)
+        .current_dir(repo.workdir().unwrap_or(repo.path()))
+        .env("GIT_INDEX_FILE", repo.path().join("index"))
+        .stdin(Stdio::null())
+        .status()
+        .map_err(|e| format!("Failed to run the {} hook: {}", name, e))?;
+
+    if !status.success() {
+        return Err(format!("The {} hook failed ({})", name, status));
+    }
+    Ok(())
+}
+
+/// Passes `message` through the `prepare-commit-msg` and, when `verify` is
+/// set, `commit-msg` hooks via `.git/COMMIT_EDITMSG`, like `git commit -m`.
+pub fn run_message_hooks(
+    repo: &Repository,
+    message: &str,
+    source: &[&str],
+    verify: bool,
+) -> Result<String, String> {
+    let message_path = repo.path().join("COMMIT_EDITMSG");
+    fs::write(&message_path, format!("{}\n", message))
+        .map_err(|e| format!("Failed to write {}: {}", message_path.display(), e))?;
+    let message_file = message_path.to_string_lossy();
+
+    let mut args = vec![message_file.as_ref()];
+    args.extend(source);
+    run(repo, PREPARE_COMMIT_MSG, &args)?;
+    if verify {
+        run(repo, COMMIT_MSG, &[&message_file])?;
+    }
+
+    let message = fs::read_to_string(&message_path)
+        .map_err(|e| format!("Failed to read {}: {}", message_path.display(), e))?;
+    // The message was composed from prompts and flags rather than an editor
+    // buffer, so `#` lines are content and not comments.
+    Ok(message::clean_whitespace(&message))
+}
+
+fn find_hook(repo: &Repository, name: &str) -> Option<PathBuf> {
+    let hooks_dir = match repo
+        .config()
+        .and_then(|config| config.get_path("core.hooksPath"))
+    {
+        Ok(path) if path.is_relative() => repo.workdir().unwrap_or(repo.path()).join(path),
+        Ok(path) => path,
+        Err(_) => repo.commondir().join("hooks"),
+    };
+
+    let path = hooks_dir.join(name);
+    if !path.is_file() {
+        return None;
+    }
+    if !is_executable(&path) {
+        eprintln!(
+            "hint: The '{}' hook was ignored because it's not set as executable.",
+            path.display()
+        );
+        return None;
+    }
+    Some(path)
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|metadata| metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_hooks_keep_lines_starting_with_a_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+
+        let message = run_message_hooks(
+            &repo,
+            "fix: handle empty input\n\n#123 follow-up  \n\n\n",
+            &["message"],
+            true,
+        )
+        .unwrap();
+
+        assert_eq!(message, "fix: handle empty input\n\n#123 follow-up\n");
+    }
+}
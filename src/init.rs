@@ -4,6 +4,7 @@ use std::{
     path::PathBuf,
 };
 
+use crate::template;
 use directories::ProjectDirs;
 use inquire::Confirm;
 use serde::{Deserialize, Serialize};
@@ -13,14 +14,61 @@ use serde::{Deserialize, Serialize};
 pub struct Config {
     pub commit: Commit,
     pub branch: Branch,
+    pub guard: Guard,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(default)]
 pub struct Commit {
     pub conventional_commits: bool,
     pub ticket_suffix: bool,
+    pub ticket_patterns: Vec<String>,
+    pub prompt_ticket: bool,
+    pub require_ticket: bool,
     pub types: Vec<String>,
+    pub type_rules: Vec<TypeRule>,
+    pub scopes: Vec<String>,
+    pub require_scope: bool,
+    pub strict_scopes: bool,
+    pub max_header_length: usize,
+    pub header_length_check: LengthCheck,
+    pub subject_case: SubjectCase,
+    pub body_leading_blank: bool,
+    pub footer_leading_blank: bool,
+    pub template: String,
+    pub body_width: usize,
+    pub trailers: bool,
+    pub trailer_tokens: Vec<String>,
+    pub co_authors: Vec<String>,
+}
+
+/// Pre-selects `type` in the commit prompt when every staged file matches
+/// one of `globs`, or with `added` set, when any matching file is new.
+/// Rules are tried in order and an empty glob list matches every file.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct TypeRule {
+    #[serde(rename = "type")]
+    pub commit_type: String,
+    #[serde(default)]
+    pub globs: Vec<String>,
+    #[serde(default)]
+    pub added: bool,
+}
+
+/// What happens when a commit header or branch name is over its limit.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LengthCheck {
+    Block,
+    Warn,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SubjectCase {
+    Any,
+    Lower,
+    Sentence,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -30,11 +78,24 @@ pub struct Branch {
     pub types: Vec<String>,
 }
 
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(default)]
+pub struct Guard {
+    pub enabled: bool,
+    pub max_file_size_kb: u64,
+    pub flag_binary: bool,
+    pub blocked_globs: Vec<String>,
+    pub secret_patterns: Vec<String>,
+}
+
 impl Default for Commit {
     fn default() -> Self {
         Self {
             conventional_commits: false,
             ticket_suffix: false,
+            ticket_patterns: vec!["[A-Z]+-[0-9]+".into()],
+            prompt_ticket: false,
+            require_ticket: false,
             types: vec![
                 "build".into(),
                 "ci".into(),
@@ -47,6 +108,43 @@ impl Default for Commit {
                 "test".into(),
                 "revert".into(),
             ],
+            type_rules: vec![
+                TypeRule::new("docs", &["*.md", "docs/*"], false),
+                TypeRule::new(
+                    "test",
+                    &["tests/*", "test/*", "*_test.*", "*.test.*", "*.spec.*"],
+                    false,
+                ),
+                TypeRule::new(
+                    "ci",
+                    &[".github/workflows/*", ".gitlab-ci.yml", ".circleci/*"],
+                    false,
+                ),
+                TypeRule::new("feat", &[], true),
+            ],
+            scopes: Vec::new(),
+            require_scope: false,
+            strict_scopes: false,
+            max_header_length: 72,
+            header_length_check: LengthCheck::Block,
+            subject_case: SubjectCase::Lower,
+            body_leading_blank: true,
+            footer_leading_blank: true,
+            template: template::DEFAULT_TEMPLATE.into(),
+            body_width: 72,
+            trailers: false,
+            trailer_tokens: vec!["Refs".into(), "Closes".into(), "Reviewed-by".into()],
+            co_authors: Vec::new(),
+        }
+    }
+}
+
+impl TypeRule {
+    fn new(commit_type: &str, globs: &[&str], added: bool) -> Self {
+        Self {
+            commit_type: commit_type.into(),
+            globs: globs.iter().map(|glob| glob.to_string()).collect(),
+            added,
         }
     }
 }
@@ -66,6 +164,35 @@ impl Default for Branch {
     }
 }
 
+impl Default for Guard {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_file_size_kb: 5 * 1024,
+            flag_binary: true,
+            blocked_globs: vec![
+                ".env".into(),
+                ".env.*".into(),
+                "*.pem".into(),
+                "*.key".into(),
+                "*.p12".into(),
+                "*.pfx".into(),
+                "id_rsa".into(),
+                "id_ed25519".into(),
+            ],
+            secret_patterns: vec![
+                r"-----BEGIN [A-Z ]*PRIVATE KEY-----".into(),
+                r"\b(AKIA|ASIA)[0-9A-Z]{16}\b".into(),
+                r"\bgh[pousr]_[A-Za-z0-9]{36,}\b".into(),
+                r"\bxox[abprs]-[A-Za-z0-9-]{10,}".into(),
+                r"\bsk_live_[0-9A-Za-z]{24,}".into(),
+                r"\bAIza[0-9A-Za-z_-]{35}\b".into(),
+                r#"(?i)(api[_-]?key|secret|token|password)\s*[:=]\s*['"][^'"\s]{16,}['"]"#.into(),
+            ],
+        }
+    }
+}
+
 pub fn load_config() -> Config {
     let config_path = get_config_path();
 
@@ -121,6 +248,26 @@ fn create_config() -> Result<Config, String> {
             get: |conf| conf.commit.ticket_suffix,
             set: |conf, val| conf.commit.ticket_suffix = val,
         },
+        Setting {
+            label: "Ask for a ticket when the branch has none?",
+            get: |conf| conf.commit.prompt_ticket,
+            set: |conf, val| conf.commit.prompt_ticket = val,
+        },
+        Setting {
+            label: "Require a ticket on every commit?",
+            get: |conf| conf.commit.require_ticket,
+            set: |conf, val| conf.commit.require_ticket = val,
+        },
+        Setting {
+            label: "Ask for trailers like Co-authored-by?",
+            get: |conf| conf.commit.trailers,
+            set: |conf, val| conf.commit.trailers = val,
+        },
+        Setting {
+            label: "Scan files for secrets before staging?",
+            get: |conf| conf.guard.enabled,
+            set: |conf, val| conf.guard.enabled = val,
+        },
         Setting {
             label: "Use conventional branches?",
             get: |conf| conf.branch.conventional_branches,
@@ -0,0 +1,207 @@
+use crate::{init::LengthCheck, picker};
+use crossterm::{
+    cursor::{MoveTo, Show},
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    queue,
+    style::{Print, Stylize},
+    terminal::{Clear, ClearType},
+};
+use std::io::{self, Write};
+
+const HELP: &str = "←→ move, home/end jump, ctrl+u clear, enter submit, esc cancel";
+
+/// A single line prompt that shows how many characters are left before the
+/// text, as measured by `measure`, runs over `limit`.
+pub struct LimitedText<'a, F: Fn(&str) -> usize> {
+    message: &'a str,
+    input: Vec<char>,
+    cursor: usize,
+    limit: usize,
+    check: LengthCheck,
+    measure: F,
+    error: Option<String>,
+}
+
+impl<'a, F: Fn(&str) -> usize> LimitedText<'a, F> {
+    pub fn new(
+        message: &'a str,
+        initial: &str,
+        limit: usize,
+        check: LengthCheck,
+        measure: F,
+    ) -> Self {
+        let input: Vec<char> = initial.chars().collect();
+        Self {
+            message,
+            cursor: input.len(),
+            input,
+            limit,
+            check,
+            measure,
+            error: None,
+        }
+    }
+
+    pub fn prompt(mut self) -> Result<String, String> {
+        let confirmed = self.run().map_err(|e| e.to_string())?;
+        if !confirmed {
+            return Err("Operation was canceled by the user".to_string());
+        }
+
+        let answer = self.text();
+        println!(
+            "{} {} {}",
+            ">".green(),
+            self.message,
+            answer.as_str().cyan()
+        );
+        Ok(answer)
+    }
+
+    fn run(&mut self) -> io::Result<bool> {
+        let _guard = picker::RawModeGuard::enter()?;
+
+        loop {
+            self.draw()?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            if let Some(confirmed) = self.handle_key(key) {
+                return Ok(confirmed);
+            }
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Option<bool> {
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Enter => match self.overflow() {
+                Some(over) if self.check == LengthCheck::Block => {
+                    self.error = Some(format!(
+                        "{} characters over the limit of {}",
+                        over, self.limit
+                    ));
+                    return None;
+                }
+                _ => return Some(true),
+            },
+            KeyCode::Esc => return Some(false),
+            KeyCode::Char('c') if control => return Some(false),
+            KeyCode::Char('a') if control => self.cursor = 0,
+            KeyCode::Char('e') if control => self.cursor = self.input.len(),
+            KeyCode::Char('u') if control => {
+                self.input.drain(..self.cursor);
+                self.cursor = 0;
+            }
+            KeyCode::Char(ch) if !control => {
+                self.input.insert(self.cursor, ch);
+                self.cursor += 1;
+            }
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.input.remove(self.cursor);
+            }
+            KeyCode::Delete if self.cursor < self.input.len() => {
+                self.input.remove(self.cursor);
+            }
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.input.len()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.input.len(),
+            _ => {}
+        }
+        self.error = None;
+        None
+    }
+
+    fn text(&self) -> String {
+        self.input.iter().collect()
+    }
+
+    fn overflow(&self) -> Option<usize> {
+        let length = (self.measure)(&self.text());
+        (self.limit > 0 && length > self.limit).then(|| length - self.limit)
+    }
+
+    fn draw(&self) -> io::Result<()> {
+        let (width, _) = picker::terminal_size();
+        let prefix = format!("? {} ", self.message);
+        let prefix_len = prefix.chars().count();
+        let input_rows = (prefix_len + self.input.len()) / width + 1;
+
+        let mut stdout = io::stdout();
+        queue!(stdout, Clear(ClearType::All), MoveTo(0, 0))?;
+        queue!(
+            stdout,
+            Print(format!("{} {} ", "?".green(), self.message)),
+            Print(self.text())
+        )?;
+
+        let mut line = input_rows as u16;
+        if self.limit > 0 {
+            let length = (self.measure)(&self.text());
+            let counter = match self.overflow() {
+                None => format!("{} characters left", self.limit - length)
+                    .dark_grey()
+                    .to_string(),
+                Some(over) if self.check == LengthCheck::Block => {
+                    format!("{} characters over the limit", over)
+                        .red()
+                        .to_string()
+                }
+                Some(over) => format!("{} characters over the limit", over)
+                    .yellow()
+                    .to_string(),
+            };
+            queue!(stdout, MoveTo(0, line), Print(counter))?;
+            line += 1;
+        }
+        if let Some(error) = &self.error {
+            queue!(stdout, MoveTo(0, line), Print(format!("# {}", error).red()))?;
+            line += 1;
+        }
+        queue!(
+            stdout,
+            MoveTo(0, line),
+            Print(HELP.chars().take(width).collect::<String>().dark_grey())
+        )?;
+
+        let position = prefix_len + self.cursor;
+        queue!(
+            stdout,
+            MoveTo((position % width) as u16, (position / width) as u16),
+            Show
+        )?;
+        stdout.flush()
+    }
+}
+
+/// Blocks or warns about `text` being longer than `limit`.
+pub fn check_length(
+    what: &str,
+    text: &str,
+    limit: usize,
+    check: LengthCheck,
+) -> Result<(), String> {
+    let length = text.chars().count();
+    if limit == 0 || length <= limit {
+        return Ok(());
+    }
+
+    match check {
+        LengthCheck::Block => Err(format!(
+            "The {} is {} characters long, the limit is {}",
+            what, length, limit
+        )),
+        LengthCheck::Warn => {
+            println!(
+                "⚠️ The {} is {} characters long, over the limit of {}.",
+                what, length, limit
+            );
+            Ok(())
+        }
+    }
+}
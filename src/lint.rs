@@ -0,0 +1,295 @@
+use crate::{
+    git_operations,
+    init::{Commit, SubjectCase},
+    message::{self, Message},
+};
+use clap::ValueEnum;
+use crossterm::style::Stylize;
+use serde::Serialize;
+use std::{
+    fs,
+    io::{self, IsTerminal, Read},
+    path::Path,
+};
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    Human,
+    Json,
+}
+
+#[derive(Serialize)]
+struct Report {
+    source: String,
+    header: String,
+    valid: bool,
+    violations: Vec<Violation>,
+}
+
+#[derive(Serialize)]
+pub struct Violation {
+    pub rule: &'static str,
+    pub message: String,
+}
+
+pub fn run_lint(
+    commit_config: Commit,
+    file: Option<&Path>,
+    range: Option<&str>,
+    format: OutputFormat,
+) -> Result<(), String> {
+    let messages = match (file, range) {
+        (_, Some(range)) => read_range(range)?,
+        (Some(path), None) if path != Path::new("-") => {
+            let text = fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            vec![(path.display().to_string(), text)]
+        }
+        _ => {
+            if io::stdin().is_terminal() {
+                return Err("pass --file, --range or pipe a message into gq lint".to_string());
+            }
+            let mut text = String::new();
+            io::stdin()
+                .read_to_string(&mut text)
+                .map_err(|e| format!("Failed to read stdin: {}", e))?;
+            vec![("stdin".to_string(), text)]
+        }
+    };
+
+    let reports: Vec<Report> = messages
+        .into_iter()
+        .map(|(source, text)| {
+            let violations = lint_message(&commit_config, &text);
+            Report {
+                source,
+                header: message::clean(&text)
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .to_string(),
+                valid: violations.is_empty(),
+                violations,
+            }
+        })
+        .collect();
+
+    match format {
+        OutputFormat::Json => {
+            let json = serde_json::to_string_pretty(&reports)
+                .map_err(|e| format!("Failed to serialize report: {}", e))?;
+            println!("{}", json);
+        }
+        OutputFormat::Human => {
+            for report in &reports {
+                let status = if report.valid { "✅" } else { "❌" };
+                println!(
+                    "{} {}: {}",
+                    status,
+                    report.source.as_str().bold(),
+                    report.header
+                );
+                for violation in &report.violations {
+                    println!("    {} {}", violation.rule.dark_grey(), violation.message);
+                }
+            }
+        }
+    }
+
+    let failed = reports.iter().filter(|report| !report.valid).count();
+    if failed > 0 {
+        return Err(format!(
+            "{} of {} message(s) failed linting",
+            failed,
+            reports.len()
+        ));
+    }
+    Ok(())
+}
+
+/// Checks a raw commit message against the configured Conventional Commits
+/// rules. Comment lines are ignored, so editor buffers can be passed as-is.
+pub fn lint_message(commit_config: &Commit, text: &str) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let mut violation = |rule, message: String| violations.push(Violation { rule, message });
+
+    let text = message::clean(text);
+    if text.is_empty() {
+        violation("header-empty", "message is empty".to_string());
+        return violations;
+    }
+
+    let parsed = Message::parse(&text);
+
+    let max_length = commit_config.max_header_length;
+    let length = parsed.header.chars().count();
+    if max_length > 0 && length > max_length {
+        violation(
+            "header-max-length",
+            format!(
+                "header is {} characters long, the limit is {}",
+                length, max_length
+            ),
+        );
+    }
+
+    match parsed.conventional_header() {
+        None => violation(
+            "header-format",
+            "header must look like `type(scope): subject`".to_string(),
+        ),
+        Some(header) => {
+            let types = &commit_config.types;
+            if !types.is_empty() && !types.contains(&header.commit_type) {
+                violation(
+                    "type-enum",
+                    format!(
+                        "type '{}' is not one of: {}",
+                        header.commit_type,
+                        types.join(", ")
+                    ),
+                );
+            }
+
+            let scopes: Vec<&str> = header
+                .scope
+                .as_deref()
+                .map(|scope| {
+                    scope
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|s| !s.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default();
+            if scopes.is_empty() && commit_config.require_scope {
+                violation("scope-empty", "scope is required".to_string());
+            }
+            let allowed = &commit_config.scopes;
+            for scope in scopes {
+                if commit_config.strict_scopes
+                    && !allowed.is_empty()
+                    && !allowed.iter().any(|allowed| allowed == scope)
+                {
+                    violation(
+                        "scope-enum",
+                        format!("scope '{}' is not one of: {}", scope, allowed.join(", ")),
+                    );
+                }
+            }
+
+            match header.subject.trim().chars().next() {
+                None => violation("subject-empty", "subject may not be empty".to_string()),
+                Some(first) => match commit_config.subject_case {
+                    SubjectCase::Lower if first.is_uppercase() => violation(
+                        "subject-case",
+                        "subject must start with a lowercase letter".to_string(),
+                    ),
+                    SubjectCase::Sentence if first.is_lowercase() => violation(
+                        "subject-case",
+                        "subject must start with an uppercase letter".to_string(),
+                    ),
+                    _ => {}
+                },
+            }
+        }
+    }
+
+    if commit_config.body_leading_blank && !parsed.blank_before_body {
+        violation(
+            "body-leading-blank",
+            "body must be separated from the header by a blank line".to_string(),
+        );
+    }
+    if commit_config.footer_leading_blank && !parsed.blank_before_footers {
+        violation(
+            "footer-leading-blank",
+            "footers must be separated from the body by a blank line".to_string(),
+        );
+    }
+
+    violations
+}
+
+fn read_range(range: &str) -> Result<Vec<(String, String)>, String> {
+    let repo = git_operations::get_repository().map_err(|e| e.to_string())?;
+
+    if !range.contains("..") {
+        let commit = repo
+            .revparse_single(range)
+            .and_then(|object| object.peel_to_commit())
+            .map_err(|e| format!("Invalid revision '{}': {}", range, e))?;
+        let source = commit.id().to_string()[..7].to_string();
+        return Ok(vec![(
+            source,
+            commit.message().unwrap_or_default().to_string(),
+        )]);
+    }
+
+    let mut revwalk = repo
+        .revwalk()
+        .map_err(|e| format!("Failed to walk history: {}", e))?;
+    revwalk
+        .push_range(range)
+        .map_err(|e| format!("Invalid range '{}': {}", range, e))?;
+    revwalk
+        .set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)
+        .map_err(|e| format!("Failed to walk history: {}", e))?;
+
+    let mut messages = Vec::new();
+    for oid in revwalk {
+        let oid = oid.map_err(|e| format!("Failed to walk history: {}", e))?;
+        let commit = repo
+            .find_commit(oid)
+            .map_err(|e| format!("Failed to read commit {}: {}", oid, e))?;
+        // Merge commits carry messages generated by git, not by the author.
+        if commit.parent_count() > 1 {
+            continue;
+        }
+        let source = oid.to_string()[..7].to_string();
+        messages.push((source, commit.message().unwrap_or_default().to_string()));
+    }
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(message: &str, commit_config: &Commit) -> Vec<&'static str> {
+        lint_message(commit_config, message)
+            .into_iter()
+            .map(|violation| violation.rule)
+            .collect()
+    }
+
+    #[test]
+    fn scope_enum_only_applies_to_strict_scopes() {
+        let mut commit_config = Commit {
+            conventional_commits: true,
+            scopes: vec!["api".to_string()],
+            ..Commit::default()
+        };
+        assert!(!rules("feat(ui): add x", &commit_config).contains(&"scope-enum"));
+
+        commit_config.strict_scopes = true;
+        assert!(rules("feat(ui): add x", &commit_config).contains(&"scope-enum"));
+        assert!(!rules("feat(api): add x", &commit_config).contains(&"scope-enum"));
+    }
+
+    #[test]
+    fn footers_need_a_blank_line_before_them() {
+        let commit_config = Commit {
+            conventional_commits: true,
+            footer_leading_blank: true,
+            ..Commit::default()
+        };
+
+        assert!(rules("feat: x\n\nsome body\nRefs: #12\n", &commit_config)
+            .contains(&"footer-leading-blank"));
+        assert!(
+            !rules("feat: x\n\nsome body\n\nRefs: #12\n", &commit_config)
+                .contains(&"footer-leading-blank")
+        );
+    }
+}
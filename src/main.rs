@@ -1,11 +1,26 @@
 use clap::{CommandFactory, Parser, Subcommand};
+use std::path::PathBuf;
 
 mod add;
+mod autosquash;
 mod branch;
 mod checkout;
 mod commit;
+mod fixup;
 mod git_operations;
+mod guard;
+mod hooks;
 mod init;
+mod limited_text;
+mod lint;
+mod message;
+mod picker;
+mod resolve;
+mod restore;
+mod signing;
+mod stash;
+mod template;
+mod unstage;
 
 #[derive(Parser)]
 #[command(name = "gq", version = env!("CARGO_PKG_VERSION"), about = "GitQuick: Simplify your git workflow")]
@@ -19,9 +34,82 @@ enum Commands {
     #[command(about = "Configure the tool")]
     Init,
     #[command(about = "Add contents of new or changed files to the index")]
-    Add,
+    Add {
+        #[arg(help = "Files to stage, as paths or globs")]
+        pathspecs: Vec<String>,
+        #[arg(short = 'p', long = "patch", help = "Pick hunks to stage")]
+        patch: bool,
+        #[arg(short = 'A', long = "all", help = "Stage all changes")]
+        all: bool,
+        #[arg(long = "modified-only", help = "Stage only changes to tracked files")]
+        modified_only: bool,
+        #[arg(
+            long = "untracked-only",
+            conflicts_with = "modified_only",
+            help = "Stage only untracked files"
+        )]
+        untracked_only: bool,
+        #[arg(short = 'f', long = "force", help = "Stage files flagged as risky")]
+        force: bool,
+    },
+    #[command(about = "Remove staged changes from the index")]
+    Unstage,
+    #[command(about = "Discard changes in the working tree")]
+    Restore {
+        #[arg(short = 'p', long = "patch", help = "Pick hunks to discard")]
+        patch: bool,
+        #[arg(long = "head", help = "Restore from HEAD instead of the index")]
+        from_head: bool,
+    },
+    #[command(about = "Stash the changes in a dirty working directory away")]
+    Stash {
+        #[arg(value_enum, help = "Stash action to run instead of asking")]
+        action: Option<stash::StashAction>,
+    },
+    #[command(about = "Resolve merge conflicts")]
+    Resolve,
     #[command(about = "Record changes to the repository")]
-    Commit,
+    Commit(commit::CommitArgs),
+    #[command(about = "Record staged changes as a fixup! or squash! commit")]
+    Fixup {
+        #[arg(help = "Commit to fix up; prompts with recent commits when left out")]
+        commit: Option<String>,
+        #[arg(long = "squash", help = "Create a squash! commit instead")]
+        squash: bool,
+        #[arg(
+            short = 'n',
+            long = "no-verify",
+            help = "Skip the pre-commit and commit-msg hooks"
+        )]
+        no_verify: bool,
+    },
+    #[command(about = "Fold fixup! and squash! commits into the commits they target")]
+    Autosquash {
+        #[arg(
+            short = 'y',
+            long = "yes",
+            help = "Rewrite already pushed commits without asking"
+        )]
+        yes: bool,
+    },
+    #[command(about = "Check commit messages against the commit rules")]
+    Lint {
+        #[arg(
+            short = 'f',
+            long = "file",
+            conflicts_with = "range",
+            help = "Read the message from a file, or - for stdin"
+        )]
+        file: Option<PathBuf>,
+        #[arg(
+            short = 'r',
+            long = "range",
+            help = "Lint every commit in a revision range such as main..HEAD"
+        )]
+        range: Option<String>,
+        #[arg(long = "format", value_enum, default_value_t = lint::OutputFormat::Human, help = "Output format")]
+        format: lint::OutputFormat,
+    },
     #[command(about = "List, create, or delete branches")]
     Branch {
         #[arg(short = 'd', long = "delete", help = "Delete a branch")]
@@ -42,20 +130,53 @@ fn main() {
     let cli = Cli::parse();
     let config = init::load_config();
     let result = match &cli.command {
-        Some(Commands::Commit) => commit::run_commit(config.commit),
+        Some(Commands::Commit(args)) => commit::run_commit(config.commit, config.guard, args),
+        Some(Commands::Fixup {
+            commit,
+            squash,
+            no_verify,
+        }) => fixup::run_fixup(commit.as_deref(), *squash, *no_verify),
+        Some(Commands::Autosquash { yes }) => autosquash::run_autosquash(*yes),
         Some(Commands::Branch {
             delete,
             force_delete,
         }) => branch::run_branch(*delete, *force_delete),
-        Some(Commands::Checkout { create_new }) => {
-            checkout::run_checkout(config.branch, *create_new)
-        }
+        Some(Commands::Checkout { create_new }) => checkout::run_checkout(
+            config.branch,
+            config.commit.max_header_length,
+            config.commit.header_length_check,
+            *create_new,
+        ),
         Some(Commands::Ignore) => {
             println!("Ignore logic to implement later");
             Ok(())
         }
         Some(Commands::Init) => init::run_config(),
-        Some(Commands::Add) => add::stage_files(),
+        Some(Commands::Add {
+            pathspecs,
+            patch,
+            all,
+            modified_only,
+            untracked_only,
+            force,
+        }) => add::stage_files(
+            config.guard,
+            pathspecs,
+            *patch,
+            *all,
+            *modified_only,
+            *untracked_only,
+            *force,
+        ),
+        Some(Commands::Lint {
+            file,
+            range,
+            format,
+        }) => lint::run_lint(config.commit, file.as_deref(), range.as_deref(), *format),
+        Some(Commands::Unstage) => unstage::unstage_files(),
+        Some(Commands::Resolve) => resolve::run_resolve(),
+        Some(Commands::Stash { action }) => stash::run_stash(*action),
+        Some(Commands::Restore { patch, from_head }) => restore::run_restore(*patch, *from_head),
         None => {
             Cli::command().print_help().unwrap();
             Ok(())
@@ -0,0 +1,279 @@
+use regex::Regex;
+use std::{fmt, sync::LazyLock};
+
+static HEADER_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?P<type>[^\s():!]+)(?:\((?P<scope>[^()]*)\))?(?P<breaking>!)?: (?P<subject>.*)$")
+        .unwrap()
+});
+static FOOTER_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?P<token>BREAKING CHANGE|[\w-]+)(?P<separator>: | #)(?P<value>.*)$").unwrap()
+});
+
+static LIST_ITEM_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(?:[-*+]|[0-9]+[.)])\s+").unwrap());
+
+const SCISSORS: &str = "# ------------------------ >8 ------------------------";
+
+/// A commit message split the way Conventional Commits describes it.
+pub struct Message {
+    pub header: String,
+    pub body: String,
+    pub footers: Vec<Footer>,
+    pub blank_before_body: bool,
+    pub blank_before_footers: bool,
+}
+
+pub struct Header {
+    pub commit_type: String,
+    pub scope: Option<String>,
+    pub breaking: bool,
+    pub subject: String,
+}
+
+pub struct Footer {
+    pub token: String,
+    pub separator: String,
+    pub value: String,
+}
+
+impl fmt::Display for Footer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}{}", self.token, self.separator, self.value)
+    }
+}
+
+/// Strips comments and surrounding whitespace like `git commit` does with
+/// its default cleanup mode.
+pub fn clean(raw: &str) -> String {
+    let raw = raw.split(SCISSORS).next().unwrap_or_default();
+    git2::message_prettify(raw, Some(b'#')).unwrap_or_else(|_| raw.trim().to_string())
+}
+
+/// Only trims trailing whitespace and blank lines, like `git commit -m`
+/// does, so lines starting with `#` such as `#123 follow-up` are kept.
+pub fn clean_whitespace(raw: &str) -> String {
+    git2::message_prettify(raw, None).unwrap_or_else(|_| raw.trim().to_string())
+}
+
+pub fn is_footer_line(line: &str) -> bool {
+    FOOTER_RE.is_match(line)
+}
+
+/// Splits a `type(scope)!: subject` header into its parts.
+pub fn parse_header(header: &str) -> Option<Header> {
+    let caps = HEADER_RE.captures(header)?;
+    Some(Header {
+        commit_type: caps["type"].to_string(),
+        scope: caps.name("scope").map(|scope| scope.as_str().to_string()),
+        breaking: caps.name("breaking").is_some(),
+        subject: caps["subject"].to_string(),
+    })
+}
+
+/// Wraps prose lines to `width` columns. Indented lines are left alone so
+/// code blocks survive, and list items keep a hanging indent.
+pub fn wrap(text: &str, width: usize) -> String {
+    if width == 0 {
+        return text.to_string();
+    }
+    text.lines()
+        .map(|line| wrap_line(line, width))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Wraps a `Token: value` footer into one line plus indented continuation
+/// lines, which is the only way git still reads it as a single trailer.
+pub fn wrap_footer(footer: &str, width: usize) -> String {
+    let unfolded = footer.split_whitespace().collect::<Vec<_>>().join(" ");
+    if width == 0 {
+        return unfolded;
+    }
+    wrap_line(&unfolded, width.saturating_sub(1).max(1))
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| {
+            if idx == 0 {
+                line.to_string()
+            } else {
+                format!(" {}", line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn wrap_line(line: &str, width: usize) -> String {
+    if line.chars().count() <= width || line.starts_with(char::is_whitespace) {
+        return line.to_string();
+    }
+
+    let indent = LIST_ITEM_RE.find(line).map_or(0, |item| item.end());
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in line.split_whitespace() {
+        let length = current.chars().count() + 1 + word.chars().count();
+        if !current.trim().is_empty() && length > width {
+            lines.push(std::mem::replace(&mut current, " ".repeat(indent)));
+        } else if !current.is_empty() && !current.ends_with(' ') {
+            current.push(' ');
+        }
+        current.push_str(word);
+    }
+    lines.push(current);
+    lines.join("\n")
+}
+
+impl Message {
+    pub fn parse(text: &str) -> Self {
+        let lines: Vec<&str> = text.trim_end().lines().collect();
+        let header = lines.first().copied().unwrap_or_default().to_string();
+        let rest = lines.get(1..).unwrap_or_default();
+
+        // Like git trailers, footers are the last paragraph, and only when
+        // every line in it is a `Token: value` pair or an indented
+        // continuation of one.
+        let paragraph_start = rest
+            .iter()
+            .rposition(|line| line.trim().is_empty())
+            .map_or(0, |idx| idx + 1);
+        let last_paragraph = &rest[paragraph_start..];
+        let is_footer_block = last_paragraph
+            .first()
+            .is_some_and(|line| is_footer_line(line))
+            && last_paragraph
+                .iter()
+                .all(|line| is_footer_line(line) || line.starts_with(char::is_whitespace));
+        let footer_start = is_footer_block.then_some(paragraph_start);
+
+        // Footers that trail the body without a blank line are not parsed as
+        // footers, but lint still has to report the missing blank line.
+        let trailing_footers = last_paragraph
+            .iter()
+            .rposition(|line| !is_footer_line(line) && !line.starts_with(char::is_whitespace))
+            .map_or(0, |idx| idx + 1);
+        let trailing_start = last_paragraph[trailing_footers..]
+            .iter()
+            .position(|line| is_footer_line(line))
+            .map(|idx| paragraph_start + trailing_footers + idx);
+
+        let (body, footer_lines) = rest.split_at(footer_start.unwrap_or(rest.len()));
+
+        let mut footers: Vec<Footer> = Vec::new();
+        for line in footer_lines {
+            match FOOTER_RE.captures(line) {
+                Some(caps) => footers.push(Footer {
+                    token: caps["token"].to_string(),
+                    separator: caps["separator"].to_string(),
+                    value: caps["value"].to_string(),
+                }),
+                // Indented lines continue the value of the previous footer.
+                None => {
+                    if let Some(footer) = footers.last_mut() {
+                        footer.value.push(' ');
+                        footer.value.push_str(line.trim());
+                    }
+                }
+            }
+        }
+
+        let blank_before_body = rest.first().is_none_or(|line| line.trim().is_empty());
+        let blank_before_footers = match trailing_start {
+            Some(0) | None => true,
+            Some(idx) => rest[idx - 1].trim().is_empty(),
+        };
+
+        Self {
+            header,
+            body: body.join("\n").trim().to_string(),
+            footers,
+            blank_before_body,
+            blank_before_footers,
+        }
+    }
+
+    pub fn conventional_header(&self) -> Option<Header> {
+        parse_header(&self.header)
+    }
+
+    pub fn breaking_change(&self) -> Option<&Footer> {
+        self.footers
+            .iter()
+            .find(|footer| footer.is_breaking_change())
+    }
+}
+
+impl Footer {
+    pub fn is_breaking_change(&self) -> bool {
+        self.token == "BREAKING CHANGE" || self.token == "BREAKING-CHANGE"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_footers_from_the_last_paragraph() {
+        let message = Message::parse(
+            "feat: add x\n\nSome body.\n\nRefs: ABC-1\nBREAKING CHANGE: drops y\n  and z\n",
+        );
+
+        assert_eq!(message.body, "Some body.");
+        let footers: Vec<String> = message.footers.iter().map(Footer::to_string).collect();
+        assert_eq!(footers, ["Refs: ABC-1", "BREAKING CHANGE: drops y and z"]);
+        assert!(message.blank_before_footers);
+        assert_eq!(message.breaking_change().unwrap().value, "drops y and z");
+    }
+
+    #[test]
+    fn prose_with_a_colon_is_not_a_footer() {
+        let message = Message::parse("fix: x\n\nThis changes things.\nExample: foo\n");
+
+        assert_eq!(message.body, "This changes things.\nExample: foo");
+        assert!(message.footers.is_empty());
+        assert!(!message.blank_before_footers);
+    }
+
+    #[test]
+    fn wrapped_footers_keep_continuation_lines_indented() {
+        let footer =
+            "BREAKING-CHANGE: the config file moved to a new place\n\nand the old one is ignored";
+        let wrapped = wrap_footer(footer, 30);
+
+        assert_eq!(
+            wrapped,
+            "BREAKING-CHANGE: the config\n file moved to a new place and\n the old one is ignored"
+        );
+        assert!(wrapped.lines().all(|line| line.chars().count() <= 30));
+        let parsed = Message::parse(&format!("feat: x\n\n{}\nCo-authored-by: A <a@b>", wrapped));
+        assert_eq!(parsed.footers.len(), 2);
+        assert_eq!(
+            parsed.footers[0].value,
+            "the config file moved to a new place and the old one is ignored"
+        );
+    }
+
+    #[test]
+    fn wraps_prose_and_keeps_code_and_lists_readable() {
+        let text =
+            "one two three four five\n    let x = one two three four five;\n- item one two three";
+
+        assert_eq!(
+            wrap(text, 12),
+            "one two\nthree four\nfive\n    let x = one two three four five;\n- item one\n  two three"
+        );
+        assert_eq!(wrap(text, 0), text);
+    }
+
+    #[test]
+    fn parses_conventional_headers() {
+        let header = parse_header("feat(api,ui)!: add x").unwrap();
+
+        assert_eq!(header.commit_type, "feat");
+        assert_eq!(header.scope.as_deref(), Some("api,ui"));
+        assert!(header.breaking);
+        assert_eq!(header.subject, "add x");
+        assert!(parse_header("add x").is_none());
+    }
+}
@@ -0,0 +1,592 @@
+use crate::git_operations::{self, Change};
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    queue,
+    style::{Print, Stylize},
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use git2::Repository;
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, IsTerminal, Write},
+};
+
+const FLAT_HELP: &str =
+    "↑↓ move, space toggle, ctrl+a all/none, type to filter, ctrl+t tree, tab diff, pgup/pgdn scroll, enter confirm, esc cancel";
+const TREE_HELP: &str =
+    "↑↓ move, space toggle, →← expand/collapse, ctrl+a all/none, type to filter, ctrl+t flat, tab diff, pgup/pgdn scroll, enter confirm, esc cancel";
+
+const LABELS: [&str; 6] = [
+    "new",
+    "modified",
+    "deleted",
+    "renamed",
+    "typechange",
+    "conflicted",
+];
+
+pub struct ChangePicker<'a> {
+    message: &'a str,
+    repo: &'a Repository,
+    changes: Vec<Change>,
+    checked: Vec<bool>,
+    filter: String,
+    search_paths: Vec<String>,
+    cursor: usize,
+    list_offset: usize,
+    tree_view: bool,
+    expanded: HashSet<String>,
+    show_preview: bool,
+    preview_offset: usize,
+    previews: HashMap<usize, Vec<(char, String)>>,
+}
+
+enum Row {
+    Dir { path: String, depth: usize },
+    File { idx: usize, depth: usize },
+}
+
+/// Fails with a readable error instead of letting a prompt read from a pipe.
+pub fn ensure_terminal(hint: &str) -> Result<(), String> {
+    if io::stdin().is_terminal() {
+        Ok(())
+    } else {
+        Err(format!("stdin is not a terminal; {}", hint))
+    }
+}
+
+pub struct RawModeGuard;
+
+impl RawModeGuard {
+    pub fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        queue!(io::stdout(), EnterAlternateScreen, Hide)?;
+        Ok(Self)
+    }
+}
+
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        let _ = queue!(io::stdout(), Show, LeaveAlternateScreen);
+        let _ = io::stdout().flush();
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+impl<'a> ChangePicker<'a> {
+    pub fn new(message: &'a str, repo: &'a Repository, mut changes: Vec<Change>) -> Self {
+        // Sorting keeps every directory's entries contiguous for the tree view.
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+        let checked = vec![false; changes.len()];
+        let search_paths = changes
+            .iter()
+            .map(|change| change.path.to_lowercase())
+            .collect();
+        Self {
+            message,
+            repo,
+            changes,
+            checked,
+            filter: String::new(),
+            search_paths,
+            cursor: 0,
+            list_offset: 0,
+            tree_view: false,
+            expanded: HashSet::new(),
+            show_preview: false,
+            preview_offset: 0,
+            previews: HashMap::new(),
+        }
+    }
+
+    /// Starts with the changes matching `is_checked` already checked.
+    pub fn with_checked(mut self, is_checked: impl Fn(&Change) -> bool) -> Self {
+        self.checked = self.changes.iter().map(is_checked).collect();
+        self
+    }
+
+    pub fn prompt(mut self) -> Result<Vec<Change>, String> {
+        let confirmed = self.run().map_err(|e| e.to_string())?;
+        if !confirmed {
+            return Err("Operation was canceled by the user".to_string());
+        }
+
+        let selected: Vec<Change> = self
+            .changes
+            .into_iter()
+            .zip(self.checked)
+            .filter_map(|(change, checked)| checked.then_some(change))
+            .collect();
+
+        let answer = selected
+            .iter()
+            .map(|change| format!("{}{}", staged_marker(change), change))
+            .collect::<Vec<_>>()
+            .join(", ");
+        println!("{} {} {}", ">".green(), self.message, answer.cyan());
+
+        Ok(selected)
+    }
+
+    fn run(&mut self) -> io::Result<bool> {
+        let _guard = RawModeGuard::enter()?;
+
+        loop {
+            self.draw()?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match self.handle_key(key) {
+                Some(confirmed) => return Ok(confirmed),
+                None => continue,
+            }
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Option<bool> {
+        let visible = self.visible_changes();
+        let rows = self.rows(&visible);
+        let last = rows.len().saturating_sub(1);
+        let control = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Enter => return Some(true),
+            KeyCode::Esc => return Some(false),
+            KeyCode::Char('c') if control => return Some(false),
+            KeyCode::Up => self.move_cursor(self.cursor.saturating_sub(1)),
+            KeyCode::Down => self.move_cursor((self.cursor + 1).min(last)),
+            KeyCode::Home => self.move_cursor(0),
+            KeyCode::End => self.move_cursor(last),
+            KeyCode::Char(' ') => match rows.get(self.cursor) {
+                Some(Row::File { idx, .. }) => self.checked[*idx] = !self.checked[*idx],
+                Some(Row::Dir { path, .. }) => {
+                    let members = members(&self.changes, &visible, path);
+                    let check = !members.iter().all(|&idx| self.checked[idx]);
+                    members.iter().for_each(|&idx| self.checked[idx] = check);
+                }
+                None => {}
+            },
+            KeyCode::Char('a') if control => {
+                let check = !visible.iter().all(|&idx| self.checked[idx]);
+                visible.iter().for_each(|&idx| self.checked[idx] = check);
+            }
+            KeyCode::Char('t') if control => {
+                self.tree_view = !self.tree_view;
+                self.move_cursor(0);
+            }
+            KeyCode::Char(ch) if !control => {
+                self.filter.push(ch);
+                self.move_cursor(0);
+            }
+            KeyCode::Backspace => {
+                self.filter.pop();
+                self.move_cursor(0);
+            }
+            KeyCode::Right if self.tree_view => {
+                if let Some(Row::Dir { path, .. }) = rows.get(self.cursor) {
+                    self.expanded.insert(path.clone());
+                }
+            }
+            KeyCode::Left if self.tree_view => match rows.get(self.cursor) {
+                Some(Row::Dir { path, .. }) if self.expanded.contains(path) => {
+                    self.expanded.remove(path);
+                }
+                // Jump to the enclosing directory, like most tree widgets do.
+                Some(Row::Dir { depth, .. }) | Some(Row::File { depth, .. }) if *depth > 0 => {
+                    let depth = *depth;
+                    let parent = rows[..self.cursor].iter().rposition(
+                        |row| matches!(row, Row::Dir { depth: d, .. } if *d == depth - 1),
+                    );
+                    if let Some(parent) = parent {
+                        self.move_cursor(parent);
+                    }
+                }
+                _ => {}
+            },
+            KeyCode::Tab => {
+                self.show_preview = !self.show_preview;
+                self.preview_offset = 0;
+            }
+            KeyCode::PageDown => self.preview_offset += self.preview_height(),
+            KeyCode::PageUp => {
+                self.preview_offset = self.preview_offset.saturating_sub(self.preview_height())
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn move_cursor(&mut self, position: usize) {
+        if position != self.cursor {
+            self.cursor = position;
+            self.preview_offset = 0;
+        }
+    }
+
+    fn rows(&self, visible: &[usize]) -> Vec<Row> {
+        if !self.tree_view {
+            return visible
+                .iter()
+                .map(|&idx| Row::File { idx, depth: 0 })
+                .collect();
+        }
+
+        let mut rows = Vec::new();
+        let mut open_dirs: Vec<&str> = Vec::new();
+
+        for &idx in visible {
+            let change = &self.changes[idx];
+            let components: Vec<&str> = change.path.split('/').collect();
+            let dirs = &components[..components.len() - 1];
+
+            let common = open_dirs
+                .iter()
+                .zip(dirs)
+                .take_while(|(open, dir)| open == dir)
+                .count();
+            open_dirs.truncate(common);
+
+            for dir in &dirs[common..] {
+                let depth = open_dirs.len();
+                open_dirs.push(dir);
+                if self.is_visible(&open_dirs[..depth]) {
+                    rows.push(Row::Dir {
+                        path: open_dirs.join("/"),
+                        depth,
+                    });
+                }
+            }
+
+            if self.is_visible(dirs) {
+                rows.push(Row::File {
+                    idx,
+                    depth: dirs.len(),
+                });
+            }
+        }
+
+        rows
+    }
+
+    /// An entry is visible when every directory above it is expanded. While
+    /// filtering, every directory with a match is shown expanded.
+    fn is_visible(&self, dirs: &[&str]) -> bool {
+        !self.filter.is_empty()
+            || (1..=dirs.len()).all(|len| self.expanded.contains(&dirs[..len].join("/")))
+    }
+
+    /// The changes whose path contains the filter, ignoring case. Worked out
+    /// once per key press or redraw and passed to everything listing rows.
+    fn visible_changes(&self) -> Vec<usize> {
+        let filter = self.filter.to_lowercase();
+        self.search_paths
+            .iter()
+            .enumerate()
+            .filter(|(_, path)| path.contains(&filter))
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    fn describe_row(&self, row: &Row, visible: &[usize]) -> String {
+        match row {
+            Row::File { idx, .. } if !self.tree_view => {
+                let change = &self.changes[*idx];
+                let checkbox = if self.checked[*idx] { "[x]" } else { "[ ]" };
+                format!("{} {}{}", checkbox, staged_marker(change), change)
+            }
+            Row::File { idx, depth } => {
+                let change = &self.changes[*idx];
+                let checkbox = if self.checked[*idx] { "[x]" } else { "[ ]" };
+                let name = change.path.rsplit('/').next().unwrap_or(&change.path);
+                let origin = change
+                    .old_path
+                    .as_ref()
+                    .map(|old_path| format!(" (from {})", old_path))
+                    .unwrap_or_default();
+                format!(
+                    "{}  {} {}{}: {}{}",
+                    "  ".repeat(*depth),
+                    checkbox,
+                    staged_marker(change),
+                    change.label(),
+                    name,
+                    origin
+                )
+            }
+            Row::Dir { path, depth } => {
+                let members = members(&self.changes, visible, path);
+                let checked = members.iter().filter(|&&idx| self.checked[idx]).count();
+                let checkbox = match checked {
+                    0 => "[ ]",
+                    n if n == members.len() => "[x]",
+                    _ => "[-]",
+                };
+                let arrow = if self.expanded.contains(path) {
+                    "▾"
+                } else {
+                    "▸"
+                };
+                let name = path.rsplit('/').next().unwrap_or(path);
+                let counts = LABELS
+                    .iter()
+                    .filter_map(|label| {
+                        let count = members
+                            .iter()
+                            .filter(|&&idx| self.changes[idx].label() == *label)
+                            .count();
+                        (count > 0).then(|| format!("{} {}", count, label))
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!(
+                    "{}{} {} {}/ ({})",
+                    "  ".repeat(*depth),
+                    arrow,
+                    checkbox,
+                    name,
+                    counts
+                )
+            }
+        }
+    }
+
+    fn list_height(&self) -> usize {
+        let (_, rows) = terminal_size();
+        // Prompt line and help line take up two rows.
+        let available = rows.saturating_sub(2).max(1);
+        if self.show_preview {
+            (available / 3).max(3).min(available)
+        } else {
+            available
+        }
+    }
+
+    fn preview_height(&self) -> usize {
+        let (_, rows) = terminal_size();
+        // The separator between list and preview takes one row.
+        rows.saturating_sub(3 + self.list_height()).max(1)
+    }
+
+    fn preview_lines(&mut self, row: Option<&Row>, visible: &[usize]) -> Vec<(char, String)> {
+        match row {
+            Some(Row::File { idx, .. }) => {
+                let repo = self.repo;
+                let change = &self.changes[*idx];
+                self.previews
+                    .entry(*idx)
+                    .or_insert_with(|| render_preview(repo, change))
+                    .clone()
+            }
+            Some(Row::Dir { path, .. }) => members(&self.changes, visible, path)
+                .iter()
+                .map(|&idx| (' ', self.changes[idx].to_string()))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn draw(&mut self) -> io::Result<()> {
+        let (width, _) = terminal_size();
+        let list_height = self.list_height();
+        let visible = self.visible_changes();
+        let rows = self.rows(&visible);
+        self.cursor = self.cursor.min(rows.len().saturating_sub(1));
+
+        if self.cursor < self.list_offset {
+            self.list_offset = self.cursor;
+        } else if self.cursor >= self.list_offset + list_height {
+            self.list_offset = self.cursor + 1 - list_height;
+        }
+
+        let mut stdout = io::stdout();
+        queue!(stdout, Clear(ClearType::All), MoveTo(0, 0))?;
+        queue!(
+            stdout,
+            Print(format!("{} {} {}", "?".green(), self.message, self.filter))
+        )?;
+
+        let mut line: u16 = 1;
+        for (position, row) in rows
+            .iter()
+            .enumerate()
+            .skip(self.list_offset)
+            .take(list_height)
+        {
+            let pointer = if position == self.cursor { ">" } else { " " };
+            let text = truncate(
+                &format!("{} {}", pointer, self.describe_row(row, &visible)),
+                width,
+            );
+            let text = if position == self.cursor {
+                text.cyan().to_string()
+            } else {
+                text
+            };
+            queue!(stdout, MoveTo(0, line), Print(text))?;
+            line += 1;
+        }
+
+        if self.show_preview {
+            let preview_height = self.preview_height();
+            let separator_row = 1 + list_height as u16;
+            queue!(
+                stdout,
+                MoveTo(0, separator_row),
+                Print("─".repeat(width).dark_grey())
+            )?;
+
+            let preview = self.preview_lines(rows.get(self.cursor), &visible);
+            let max_offset = preview.len().saturating_sub(preview_height);
+            self.preview_offset = self.preview_offset.min(max_offset);
+
+            for (line_idx, (origin, text)) in preview
+                .iter()
+                .skip(self.preview_offset)
+                .take(preview_height)
+                .enumerate()
+            {
+                let text = truncate(text, width);
+                let text = match origin {
+                    '+' => text.green().to_string(),
+                    '-' => text.red().to_string(),
+                    '@' => text.cyan().to_string(),
+                    _ => text,
+                };
+                queue!(
+                    stdout,
+                    MoveTo(0, separator_row + 1 + line_idx as u16),
+                    Print(text)
+                )?;
+            }
+            line = separator_row + 1 + preview_height as u16;
+        }
+
+        let help = if self.tree_view { TREE_HELP } else { FLAT_HELP };
+        queue!(
+            stdout,
+            MoveTo(0, line),
+            Print(truncate(help, width).dark_grey())
+        )?;
+        stdout.flush()
+    }
+}
+
+/// The visible changes under `dir`. Changes are sorted by path, so they
+/// sit next to each other and are found without scanning the whole list.
+fn members<'v>(changes: &[Change], visible: &'v [usize], dir: &str) -> &'v [usize] {
+    let prefix = format!("{}/", dir);
+    let start = visible.partition_point(|&idx| changes[idx].path < prefix);
+    let len = visible[start..]
+        .iter()
+        .take_while(|&&idx| changes[idx].path.starts_with(&prefix))
+        .count();
+    &visible[start..start + len]
+}
+
+/// Tells the index side of a file apart from its working tree changes when
+/// the picker lists both.
+fn staged_marker(change: &Change) -> &'static str {
+    if change.is_staged() {
+        "staged "
+    } else {
+        ""
+    }
+}
+
+fn render_preview(repo: &Repository, change: &Change) -> Vec<(char, String)> {
+    let hunks = match git_operations::get_hunks(repo, change) {
+        Ok(hunks) => hunks,
+        Err(e) => return vec![(' ', format!("Failed to load diff: {}", e))],
+    };
+
+    if hunks.is_empty() {
+        return vec![(' ', "No textual changes to show.".to_string())];
+    }
+
+    let mut lines = Vec::new();
+    for hunk in hunks {
+        lines.push(('@', hunk.header.clone()));
+        for line in &hunk.lines {
+            lines.push((line.origin, line.to_string().replace('\t', "    ")));
+        }
+    }
+    lines
+}
+
+pub fn terminal_size() -> (usize, usize) {
+    match terminal::size() {
+        Ok((columns, rows)) if columns > 0 && rows > 0 => (columns as usize, rows as usize),
+        _ => (80, 24),
+    }
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn press(picker: &mut ChangePicker, code: KeyCode, modifiers: KeyModifiers) {
+        picker.handle_key(KeyEvent::new(code, modifiers));
+    }
+
+    #[test]
+    fn typing_filters_the_list_and_select_all_only_checks_matches() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        for path in ["README.md", "src/app.rs", "src/lib.rs"] {
+            fs::write(dir.path().join(path), "x\n").unwrap();
+        }
+        let (changes, _) = git_operations::get_changes(&repo);
+        let mut picker = ChangePicker::new("Select", &repo, changes);
+
+        for ch in "SRC/".chars() {
+            press(&mut picker, KeyCode::Char(ch), KeyModifiers::NONE);
+        }
+        let visible: Vec<&str> = picker
+            .visible_changes()
+            .into_iter()
+            .map(|idx| picker.changes[idx].path.as_str())
+            .collect();
+        assert_eq!(visible, ["src/app.rs", "src/lib.rs"]);
+
+        press(&mut picker, KeyCode::Char('a'), KeyModifiers::CONTROL);
+        press(&mut picker, KeyCode::Backspace, KeyModifiers::NONE);
+        press(&mut picker, KeyCode::Char('j'), KeyModifiers::NONE);
+        assert_eq!(picker.filter, "SRCj");
+        assert_eq!(picker.checked, [false, true, true]);
+    }
+
+    #[test]
+    fn toggling_a_directory_checks_only_files_under_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        for sub in ["a", "a-b", "ab"] {
+            fs::create_dir(dir.path().join(sub)).unwrap();
+        }
+        for path in ["a/x", "a/y", "a-b/z", "ab/z", "b"] {
+            fs::write(dir.path().join(path), "x\n").unwrap();
+        }
+        let (changes, _) = git_operations::get_changes(&repo);
+        let mut picker = ChangePicker::new("Select", &repo, changes);
+
+        press(&mut picker, KeyCode::Char('t'), KeyModifiers::CONTROL);
+        press(&mut picker, KeyCode::Down, KeyModifiers::NONE);
+        press(&mut picker, KeyCode::Char(' '), KeyModifiers::NONE);
+
+        let checked: Vec<&str> = picker
+            .changes
+            .iter()
+            .zip(&picker.checked)
+            .filter_map(|(change, &checked)| checked.then_some(change.path.as_str()))
+            .collect();
+        assert_eq!(checked, ["a/x", "a/y"]);
+    }
+}
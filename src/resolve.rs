@@ -0,0 +1,104 @@
+use crate::git_operations::{self, Conflict, ConflictSide};
+use crossterm::style::Stylize;
+use inquire::Select;
+use std::fmt;
+
+#[derive(Clone, Copy, PartialEq)]
+enum ConflictAction {
+    TakeOurs,
+    TakeTheirs,
+    OpenInEditor,
+    MarkResolved,
+    Skip,
+}
+
+impl fmt::Display for ConflictAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let label = match self {
+            ConflictAction::TakeOurs => "Take ours",
+            ConflictAction::TakeTheirs => "Take theirs",
+            ConflictAction::OpenInEditor => "Open in $EDITOR",
+            ConflictAction::MarkResolved => "Mark resolved",
+            ConflictAction::Skip => "Skip",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+pub fn run_resolve() -> Result<(), String> {
+    let repo = git_operations::get_repository().map_err(|e| e.to_string())?;
+
+    let mut index = repo
+        .index()
+        .map_err(|e| format!("Error accessing index: {}", e))?;
+
+    let conflicts = git_operations::get_conflicts(&index)
+        .map_err(|e| format!("Failed to read conflicts: {}", e))?;
+
+    if conflicts.is_empty() {
+        println!("No conflicts found.");
+        return Ok(());
+    }
+
+    println!("Conflicted files:");
+    for conflict in &conflicts {
+        println!("  {}", conflict.to_string().red());
+    }
+
+    for conflict in &conflicts {
+        resolve_one(&repo, &mut index, conflict)?;
+    }
+
+    let remaining = git_operations::get_conflicts(&index)
+        .map_err(|e| format!("Failed to read conflicts: {}", e))?
+        .len();
+
+    if remaining == 0 {
+        println!("✅ All conflicts resolved!");
+    } else {
+        println!("⚠️ {} conflict(s) left.", remaining);
+    }
+    Ok(())
+}
+
+fn resolve_one(
+    repo: &git2::Repository,
+    index: &mut git2::Index,
+    conflict: &Conflict,
+) -> Result<(), String> {
+    loop {
+        let actions = vec![
+            ConflictAction::TakeOurs,
+            ConflictAction::TakeTheirs,
+            ConflictAction::OpenInEditor,
+            ConflictAction::MarkResolved,
+            ConflictAction::Skip,
+        ];
+        let action = Select::new(&format!("Resolve {}", conflict), actions)
+            .prompt()
+            .map_err(|e| format!("An error occurred during selection: {}", e))?;
+
+        let side = match action {
+            ConflictAction::TakeOurs => ConflictSide::Ours,
+            ConflictAction::TakeTheirs => ConflictSide::Theirs,
+            ConflictAction::OpenInEditor => {
+                let workdir = repo
+                    .workdir()
+                    .ok_or("Repository has no working directory")?;
+                git_operations::open_in_editor(repo, &workdir.join(&conflict.path))
+                    .map_err(|e| format!("Failed to open editor: {}", e))?;
+                continue;
+            }
+            ConflictAction::MarkResolved => {
+                git_operations::mark_resolved(index, &conflict.path)
+                    .map_err(|e| format!("Failed to mark {} resolved: {}", conflict.path, e))?;
+                return Ok(());
+            }
+            ConflictAction::Skip => return Ok(()),
+        };
+
+        git_operations::resolve_conflict(repo, index, conflict, side)
+            .map_err(|e| format!("Failed to resolve {}: {}", conflict.path, e))?;
+        return Ok(());
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{
+    add,
+    git_operations::{self, Change, Hunk},
+    picker::{self, ChangePicker},
+};
+use inquire::Confirm;
+
+pub fn run_restore(patch: bool, from_head: bool) -> Result<(), String> {
+    let repo = git_operations::get_repository().map_err(|e| e.to_string())?;
+
+    let (mut changes, staged) = git_operations::get_changes(&repo);
+
+    // Restoring from HEAD also throws away what is staged.
+    if from_head {
+        for change in staged {
+            if !changes.iter().any(|existing| existing.path == change.path) {
+                changes.push(change);
+            }
+        }
+    }
+
+    if changes.is_empty() {
+        println!("No changes to restore.");
+        return Ok(());
+    }
+
+    picker::ensure_terminal("gq restore needs an interactive terminal")?;
+
+    let selected = ChangePicker::new("Select changes to discard:", &repo, changes)
+        .prompt()
+        .map_err(|e| format!("An error occurred during selection: {}", e))?;
+
+    if selected.is_empty() {
+        println!("No files selected.");
+        return Ok(());
+    }
+
+    let mut whole_files = Vec::<Change>::new();
+    let mut partial_files = Vec::<(Change, Vec<Hunk>)>::new();
+
+    for change in selected {
+        if !patch || from_head || !change.supports_hunks() {
+            whole_files.push(change);
+            continue;
+        }
+
+        let hunks = git_operations::get_hunks(&repo, &change)
+            .map_err(|e| format!("Failed to diff {}: {}", change.path, e))?;
+        if hunks.is_empty() {
+            whole_files.push(change);
+            continue;
+        }
+
+        let hunks = add::select_hunks(&change, hunks, "Discard")?;
+        if hunks.iter().any(Hunk::has_selection) {
+            partial_files.push((change, hunks));
+        }
+    }
+
+    let file_count = whole_files.len() + partial_files.len();
+    if file_count == 0 {
+        println!("Nothing selected to discard.");
+        return Ok(());
+    }
+
+    let should_restore = Confirm::new(&format!("Discard changes in {} file(s)?", file_count))
+        .with_default(false)
+        .prompt()
+        .map_err(|e| format!("Failed to get confirmation: {}", e))?;
+
+    if !should_restore {
+        println!("❌ Restore canceled or failed to get user confirmation.");
+        return Ok(());
+    }
+
+    let backup_files: Vec<Change> = whole_files
+        .iter()
+        .cloned()
+        .chain(partial_files.iter().map(|(change, _)| change.clone()))
+        .collect();
+    let paths: Vec<&str> = backup_files
+        .iter()
+        .map(|change| change.path.as_str())
+        .collect();
+    let message = format!("gq restore backup\n\n{}", paths.join("\n"));
+    let backup = git_operations::create_stash_commit(&repo, &backup_files, &message, from_head)
+        .map_err(|e| format!("Failed to save a backup, nothing was discarded: {}", e))?;
+
+    for (change, hunks) in &partial_files {
+        git_operations::discard_hunks(&repo, change, hunks)
+            .map_err(|e| format!("Failed to discard hunks of {}: {}", change.path, e))?;
+    }
+
+    if !whole_files.is_empty() {
+        git_operations::restore_files(&repo, whole_files, from_head)
+            .map_err(|e| format!("Failed to restore files: {}", e))?;
+    }
+
+    println!("✅ Restored files successfuly!");
+    println!(
+        "Backup saved as {}, bring it back with `git stash apply {}`",
+        backup, backup
+    );
+    Ok(())
+}
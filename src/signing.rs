@@ -0,0 +1,241 @@
+use git2::{Config, Signature};
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+/// Signs a commit buffer the way `git commit` does when `commit.gpgsign` is
+/// set, honoring `gpg.format`, `gpg.<format>.program` and `user.signingkey`.
+/// Returns `None` when commits are not meant to be signed.
+pub fn sign_commit(
+    config: &Config,
+    buffer: &str,
+    committer: &Signature,
+) -> Result<Option<String>, git2::Error> {
+    if !config.get_bool("commit.gpgsign").unwrap_or(false) {
+        return Ok(None);
+    }
+
+    let format = config
+        .get_string("gpg.format")
+        .unwrap_or_else(|_| "openpgp".to_string());
+    let signing_key = config
+        .get_string("user.signingkey")
+        .ok()
+        .filter(|key| !key.trim().is_empty());
+
+    let signature = match format.as_str() {
+        "openpgp" | "x509" => {
+            let default_program = if format == "x509" { "gpgsm" } else { "gpg" };
+            let program = config
+                .get_string(&format!("gpg.{}.program", format))
+                .or_else(|_| config.get_string("gpg.program"))
+                .unwrap_or_else(|_| default_program.to_string());
+            // Without a configured key git signs as the committer.
+            let key = signing_key.unwrap_or_else(|| committer.to_string());
+            sign_with_gpg(&program, &key, buffer)
+        }
+        "ssh" => {
+            let program = config
+                .get_string("gpg.ssh.program")
+                .unwrap_or_else(|_| "ssh-keygen".to_string());
+            let key = signing_key.ok_or_else(|| {
+                git2::Error::from_str("user.signingkey is required for SSH signing")
+            })?;
+            sign_with_ssh(&program, &key, buffer)
+        }
+        other => {
+            return Err(git2::Error::from_str(&format!(
+                "unsupported gpg.format '{}'",
+                other
+            )))
+        }
+    };
+
+    signature
+        .map(Some)
+        .map_err(|e| git2::Error::from_str(&format!("failed to sign the commit: {}", e)))
+}
+
+fn sign_with_gpg(program: &str, key: &str, buffer: &str) -> io::Result<String> {
+    let mut child = Command::new(program)
+        .args(["--status-fd=2", "-bsau", key])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| io::Error::other(format!("cannot run {}: {}", program, e)))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(buffer.as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+
+    // gpg may exit successfully without signing, so look for its status line.
+    let status = String::from_utf8_lossy(&output.stderr);
+    let signed = status
+        .lines()
+        .any(|line| line.starts_with("[GNUPG:] SIG_CREATED "));
+    if !output.status.success() || !signed {
+        let messages: Vec<&str> = status
+            .lines()
+            .filter(|line| !line.starts_with("[GNUPG:]"))
+            .collect();
+        return Err(io::Error::other(format!(
+            "{} failed: {}",
+            program,
+            messages.join("\n")
+        )));
+    }
+    String::from_utf8(output.stdout).map_err(io::Error::other)
+}
+
+fn sign_with_ssh(program: &str, key: &str, buffer: &str) -> io::Result<String> {
+    let mut payload = tempfile::NamedTempFile::new()?;
+    payload.write_all(buffer.as_bytes())?;
+
+    // A literal public key means the private half lives in ssh-agent.
+    let mut literal_key = None;
+    let key_path = match key
+        .strip_prefix("key::")
+        .or(Some(key).filter(|key| key.starts_with("ssh-")))
+    {
+        Some(public_key) => {
+            let mut file = tempfile::NamedTempFile::new()?;
+            writeln!(file, "{}", public_key)?;
+            let path = file.path().to_path_buf();
+            literal_key = Some(file);
+            path
+        }
+        None => expand_home(key),
+    };
+
+    let mut command = Command::new(program);
+    command
+        .args(["-Y", "sign", "-n", "git", "-f"])
+        .arg(&key_path);
+    if literal_key.is_some() {
+        command.arg("-U");
+    }
+    let output = command
+        .arg(payload.path())
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| io::Error::other(format!("cannot run {}: {}", program, e)))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(format!(
+            "{} failed: {}",
+            program,
+            stderr.trim()
+        )));
+    }
+
+    let signature_path = PathBuf::from(format!("{}.sig", payload.path().display()));
+    let signature = std::fs::read_to_string(&signature_path);
+    let _ = std::fs::remove_file(&signature_path);
+    signature
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), directories::BaseDirs::new()) {
+        (Some(rest), Some(dirs)) => dirs.home_dir().join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::{fs, path::Path};
+
+    /// Creates a passphrase-less ed25519 key in `dir` and returns the path of
+    /// the private key.
+    pub(crate) fn generate_ssh_key(dir: &Path) -> PathBuf {
+        let key = dir.join("signing_key");
+        let status = Command::new("ssh-keygen")
+            .args(["-q", "-t", "ed25519", "-N", "", "-C", "gq-test", "-f"])
+            .arg(&key)
+            .status()
+            .expect("ssh-keygen is needed to run the signing tests");
+        assert!(status.success());
+        key
+    }
+
+    fn ssh_config(dir: &Path, key: &Path) -> Config {
+        let path = dir.join("config");
+        fs::write(
+            &path,
+            format!(
+                "[commit]\n\tgpgsign = true\n[gpg]\n\tformat = ssh\n[user]\n\tsigningkey = {}\n",
+                key.display()
+            ),
+        )
+        .unwrap();
+        Config::open(&path).unwrap()
+    }
+
+    #[test]
+    fn signs_with_an_ssh_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = generate_ssh_key(dir.path());
+        let buffer = "tree 4b825dc642cb6eb9a060e54bf8d69288fbee4904\n\nmessage\n";
+
+        let signature = sign_with_ssh("ssh-keygen", &key.to_string_lossy(), buffer).unwrap();
+        assert!(signature.starts_with("-----BEGIN SSH SIGNATURE-----"));
+
+        // The signature has to verify against the exact buffer.
+        let signature_path = dir.path().join("buffer.sig");
+        fs::write(&signature_path, &signature).unwrap();
+        let mut check = Command::new("ssh-keygen")
+            .args(["-Y", "check-novalidate", "-n", "git", "-s"])
+            .arg(&signature_path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        check
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(buffer.as_bytes())
+            .unwrap();
+        assert!(check.wait().unwrap().success());
+    }
+
+    #[test]
+    fn sign_commit_follows_the_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = generate_ssh_key(dir.path());
+        let committer = Signature::now("Test", "test@example.com").unwrap();
+
+        let config = ssh_config(dir.path(), &key);
+        let signature = sign_commit(&config, "payload\n", &committer).unwrap();
+        assert!(signature.is_some_and(|signature| signature.contains("SSH SIGNATURE")));
+    }
+
+    #[test]
+    fn sign_commit_skips_unsigned_repositories() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config");
+        fs::write(&path, "[commit]\n\tgpgsign = false\n").unwrap();
+        let config = Config::open(&path).unwrap();
+        let committer = Signature::now("Test", "test@example.com").unwrap();
+
+        assert_eq!(sign_commit(&config, "payload\n", &committer).unwrap(), None);
+    }
+
+    #[test]
+    fn ssh_signing_needs_a_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config");
+        fs::write(&path, "[commit]\n\tgpgsign = true\n[gpg]\n\tformat = ssh\n").unwrap();
+        let config = Config::open(&path).unwrap();
+        let committer = Signature::now("Test", "test@example.com").unwrap();
+
+        assert!(sign_commit(&config, "payload\n", &committer).is_err());
+    }
+}
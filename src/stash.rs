@@ -0,0 +1,204 @@
+use crate::{
+    git_operations::{self, Change, StashEntry},
+    picker::{self, ChangePicker},
+};
+use clap::ValueEnum;
+use crossterm::style::Stylize;
+use git2::Repository;
+use inquire::{Confirm, Select, Text};
+use std::fmt;
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum StashAction {
+    Push,
+    List,
+    Apply,
+    Pop,
+    Drop,
+    Show,
+}
+
+impl fmt::Display for StashAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let label = match self {
+            StashAction::Push => "push",
+            StashAction::List => "list",
+            StashAction::Apply => "apply",
+            StashAction::Pop => "pop",
+            StashAction::Drop => "drop",
+            StashAction::Show => "show",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+pub fn run_stash(action: Option<StashAction>) -> Result<(), String> {
+    let mut repo = git_operations::get_repository().map_err(|e| e.to_string())?;
+
+    let action = match action {
+        Some(action) => action,
+        None => {
+            picker::ensure_terminal("pass a stash action such as `gq stash list`")?;
+            Select::new(
+                "Select stash action",
+                StashAction::value_variants().to_vec(),
+            )
+            .prompt()
+            .map_err(|e| format!("An error occurred during selection: {}", e))?
+        }
+    };
+
+    match action {
+        StashAction::Push => push_stash(&mut repo),
+        StashAction::List => list_stashes(&mut repo),
+        _ => {
+            let Some(entry) = select_stash(&mut repo, action)? else {
+                println!("No stash entries found.");
+                return Ok(());
+            };
+            match action {
+                StashAction::Apply => {
+                    repo.stash_apply(entry.index, None)
+                        .map_err(|e| format!("Failed to apply {}: {}", entry, e))?;
+                    println!("✅ Applied {}", entry);
+                }
+                StashAction::Pop => {
+                    repo.stash_pop(entry.index, None)
+                        .map_err(|e| format!("Failed to pop {}: {}", entry, e))?;
+                    println!("✅ Popped {}", entry);
+                }
+                StashAction::Drop => drop_stash(&mut repo, &entry)?,
+                _ => show_stash(&repo, &entry)?,
+            }
+            Ok(())
+        }
+    }
+}
+
+fn push_stash(repo: &mut Repository) -> Result<(), String> {
+    let (mut changes, staged) = git_operations::get_changes(repo);
+    for change in staged {
+        if !changes.iter().any(|existing| existing.path == change.path) {
+            changes.push(change);
+        }
+    }
+
+    if changes.is_empty() {
+        println!("No local changes to save.");
+        return Ok(());
+    }
+
+    picker::ensure_terminal("gq stash push needs an interactive terminal")?;
+
+    let message = Text::new("Stash message:")
+        .prompt()
+        .map_err(|e| format!("An error occurred: {}", e))?;
+    let message = Some(message.trim()).filter(|message| !message.is_empty());
+
+    let only_selected = Confirm::new("Stash only selected files?")
+        .with_default(false)
+        .prompt()
+        .map_err(|e| format!("Failed to get confirmation: {}", e))?;
+
+    let (selected, include_untracked) = if only_selected {
+        let selected = ChangePicker::new("Select changes to stash:", repo, changes)
+            .prompt()
+            .map_err(|e| format!("An error occurred during selection: {}", e))?;
+        if selected.is_empty() {
+            println!("No files selected.");
+            return Ok(());
+        }
+        // Picked untracked files are always part of the stash.
+        (selected, false)
+    } else if changes.iter().any(Change::is_untracked) {
+        let include_untracked = Confirm::new("Include untracked files?")
+            .with_default(false)
+            .prompt()
+            .map_err(|e| format!("Failed to get confirmation: {}", e))?;
+        (Vec::new(), include_untracked)
+    } else {
+        (Vec::new(), false)
+    };
+
+    git_operations::stash_push(repo, message, &selected, include_untracked)
+        .map_err(|e| format!("Failed to stash changes: {}", e))?;
+
+    println!("✅ Stashed changes successfuly!");
+    Ok(())
+}
+
+fn list_stashes(repo: &mut Repository) -> Result<(), String> {
+    let stashes =
+        git_operations::get_stashes(repo).map_err(|e| format!("Failed to read stashes: {}", e))?;
+
+    if stashes.is_empty() {
+        println!("No stash entries found.");
+        return Ok(());
+    }
+
+    for entry in &stashes {
+        println!("{}", entry.to_string().bold());
+        let diffs = git_operations::get_stash_diffs(repo, entry.id)
+            .map_err(|e| format!("Failed to diff {}: {}", entry, e))?;
+        for diff in &diffs {
+            for delta in diff.deltas() {
+                if let Some(path) = delta.new_file().path().or(delta.old_file().path()) {
+                    println!("    {}", path.display().to_string().dark_grey());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn select_stash(repo: &mut Repository, action: StashAction) -> Result<Option<StashEntry>, String> {
+    let stashes =
+        git_operations::get_stashes(repo).map_err(|e| format!("Failed to read stashes: {}", e))?;
+
+    if stashes.is_empty() {
+        return Ok(None);
+    }
+
+    picker::ensure_terminal("gq stash needs an interactive terminal to pick an entry")?;
+
+    Select::new(&format!("Select stash to {}", action), stashes)
+        .prompt()
+        .map(Some)
+        .map_err(|e| format!("An error occurred during selection: {}", e))
+}
+
+fn drop_stash(repo: &mut Repository, entry: &StashEntry) -> Result<(), String> {
+    let should_drop = Confirm::new(&format!("Drop {}?", entry))
+        .with_default(false)
+        .prompt()
+        .map_err(|e| format!("Failed to get confirmation: {}", e))?;
+
+    if should_drop {
+        repo.stash_drop(entry.index)
+            .map_err(|e| format!("Failed to drop {}: {}", entry, e))?;
+        println!("✅ Dropped {} ({})", entry, entry.id);
+    } else {
+        println!("❌ Drop canceled or failed to get user confirmation.");
+    }
+    Ok(())
+}
+
+fn show_stash(repo: &Repository, entry: &StashEntry) -> Result<(), String> {
+    let diffs = git_operations::get_stash_diffs(repo, entry.id)
+        .map_err(|e| format!("Failed to diff {}: {}", entry, e))?;
+
+    for diff in &diffs {
+        let lines = git_operations::get_diff_lines(diff)
+            .map_err(|e| format!("Failed to diff {}: {}", entry, e))?;
+        for (origin, text) in lines {
+            match origin {
+                '+' => println!("{}", text.green()),
+                '-' => println!("{}", text.red()),
+                '@' => println!("{}", text.cyan()),
+                'F' => println!("{}", text.bold()),
+                _ => println!("{}", text),
+            }
+        }
+    }
+    Ok(())
+}
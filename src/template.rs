@@ -0,0 +1,422 @@
+use crate::message;
+use regex::Regex;
+use std::ops::Range;
+
+/// Reproduces the layout gq has always used: `type(scope)!: subject (TICKET)`.
+pub const DEFAULT_TEMPLATE: &str =
+    "[{type}[({scope})]{breaking}: ]{subject}[ ({ticket})][\n\n{body}][\n\n{footers}]";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Field {
+    Type,
+    Scope,
+    Breaking,
+    Subject,
+    Ticket,
+    Body,
+    Footers,
+}
+
+enum Part {
+    Literal(String),
+    Placeholder(Field),
+    Group(Vec<Part>),
+}
+
+/// Everything a template can place in a commit message.
+#[derive(Default, Clone)]
+pub struct Values {
+    pub commit_type: String,
+    pub scope: String,
+    pub breaking: bool,
+    pub subject: String,
+    pub ticket: String,
+    pub body: String,
+    pub footers: Vec<String>,
+}
+
+/// A commit message layout. `{name}` inserts a value and `[...]` marks an
+/// optional group that is left out when all placeholders inside it are
+/// empty. `\[`, `\]`, `\{`, `\}` and `\\` insert the character itself, and
+/// `\n` a line break for templates written as TOML literal strings.
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "type" => Some(Field::Type),
+            "scope" => Some(Field::Scope),
+            "breaking" => Some(Field::Breaking),
+            "subject" => Some(Field::Subject),
+            "ticket" => Some(Field::Ticket),
+            "body" => Some(Field::Body),
+            "footers" => Some(Field::Footers),
+            _ => None,
+        }
+    }
+
+    fn value(self, values: &Values) -> String {
+        match self {
+            Field::Type => values.commit_type.clone(),
+            Field::Scope => values.scope.clone(),
+            Field::Breaking => if values.breaking { "!" } else { "" }.to_string(),
+            Field::Subject => values.subject.clone(),
+            Field::Ticket => values.ticket.clone(),
+            Field::Body => values.body.clone(),
+            Field::Footers => values.footers.join("\n"),
+        }
+    }
+}
+
+impl Template {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut chars = text.chars();
+        let parts = parse_parts(&mut chars, false)?;
+        Ok(Self { parts })
+    }
+
+    pub fn render(&self, values: &Values) -> String {
+        let mut message = String::new();
+        let mut footer_ranges = Vec::new();
+        render_parts(&self.parts, values, &mut message, &mut footer_ranges);
+        join_footer_paragraphs(&message, &footer_ranges)
+    }
+
+    /// Whether `{ticket}` starts a line after `prefix`, as in `Refs: {ticket}`.
+    pub fn has_ticket_footer(&self, prefix: &str) -> bool {
+        ticket_after(&self.parts, prefix)
+    }
+
+    /// Reads the values back out of a header line rendered by this template.
+    pub fn match_header(&self, header: &str, ticket_pattern: &str) -> Option<Values> {
+        let mut pattern = String::from("^");
+        let mut seen = Vec::new();
+        header_pattern(&self.parts, ticket_pattern, &mut seen, &mut pattern);
+        pattern.push('$');
+
+        let caps = Regex::new(&pattern).ok()?.captures(header)?;
+        let capture = |name: &str| {
+            caps.name(name)
+                .map(|value| value.as_str().to_string())
+                .unwrap_or_default()
+        };
+
+        Some(Values {
+            commit_type: capture("type"),
+            scope: capture("scope"),
+            breaking: caps.name("breaking").is_some(),
+            subject: capture("subject"),
+            ticket: capture("ticket"),
+            ..Values::default()
+        })
+    }
+}
+
+fn parse_parts(chars: &mut std::str::Chars, in_group: bool) -> Result<Vec<Part>, String> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => match chars.next() {
+                Some(escaped @ ('[' | ']' | '{' | '}' | '\\')) => literal.push(escaped),
+                Some('n') => literal.push('\n'),
+                Some(other) => return Err(format!("unknown escape '\\{}'", other)),
+                None => return Err("template ends with a lone '\\'".to_string()),
+            },
+            '{' => {
+                let name: String = chars.by_ref().take_while(|&ch| ch != '}').collect();
+                let field = Field::from_name(&name)
+                    .ok_or_else(|| format!("unknown placeholder '{{{}}}'", name))?;
+                flush_literal(&mut literal, &mut parts);
+                parts.push(Part::Placeholder(field));
+            }
+            '[' => {
+                flush_literal(&mut literal, &mut parts);
+                parts.push(Part::Group(parse_parts(chars, true)?));
+            }
+            ']' if in_group => {
+                flush_literal(&mut literal, &mut parts);
+                return Ok(parts);
+            }
+            ']' => return Err("unmatched ']'".to_string()),
+            '}' => return Err("unmatched '}'".to_string()),
+            _ => literal.push(ch),
+        }
+    }
+
+    if in_group {
+        return Err("unclosed '['".to_string());
+    }
+    flush_literal(&mut literal, &mut parts);
+    Ok(parts)
+}
+
+fn flush_literal(literal: &mut String, parts: &mut Vec<Part>) {
+    if !literal.is_empty() {
+        parts.push(Part::Literal(std::mem::take(literal)));
+    }
+}
+
+/// Renders `parts` into `out` and records which spans of it are footers:
+/// the `{footers}` value and `{ticket}` lines such as `Refs: {ticket}`.
+fn render_parts(
+    parts: &[Part],
+    values: &Values,
+    out: &mut String,
+    footer_ranges: &mut Vec<Range<usize>>,
+) {
+    for (idx, part) in parts.iter().enumerate() {
+        match part {
+            Part::Literal(text) => out.push_str(text),
+            Part::Placeholder(field) => {
+                let start = match (field, idx.checked_sub(1).map(|prev| &parts[prev])) {
+                    (Field::Footers, _) => Some(out.len()),
+                    (Field::Ticket, Some(Part::Literal(text))) if text.contains('\n') => {
+                        let line = text.rsplit('\n').next().unwrap_or_default();
+                        message::is_footer_line(&format!("{}x", line))
+                            .then(|| out.len() - line.len())
+                    }
+                    _ => None,
+                };
+                out.push_str(&field.value(values));
+                if let Some(start) = start {
+                    footer_ranges.push(start..out.len());
+                }
+            }
+            Part::Group(inner) => {
+                if has_value(inner, values) {
+                    render_parts(inner, values, out, footer_ranges);
+                }
+            }
+        }
+    }
+}
+
+fn has_value(parts: &[Part], values: &Values) -> bool {
+    parts.iter().any(|part| match part {
+        Part::Literal(_) => false,
+        Part::Placeholder(field) => !field.value(values).is_empty(),
+        Part::Group(inner) => has_value(inner, values),
+    })
+}
+
+/// Appends the regex for the first line of `parts` to `pattern` and returns
+/// whether the end of that line was reached.
+fn header_pattern(
+    parts: &[Part],
+    ticket_pattern: &str,
+    seen: &mut Vec<Field>,
+    pattern: &mut String,
+) -> bool {
+    for part in parts {
+        match part {
+            Part::Literal(text) => {
+                let line = text.split('\n').next().unwrap_or_default();
+                pattern.push_str(&regex::escape(line));
+                if line.len() < text.len() {
+                    return true;
+                }
+            }
+            Part::Placeholder(Field::Body | Field::Footers) => return true,
+            Part::Placeholder(field) => {
+                let value = match field {
+                    Field::Type => r"[^\s():!]+",
+                    Field::Scope => r"[^()]*",
+                    Field::Breaking => "!",
+                    Field::Ticket => ticket_pattern,
+                    _ => ".+?",
+                };
+                // A field used twice can only be captured once.
+                if seen.contains(field) {
+                    pattern.push_str(&format!("(?:{})", value));
+                } else {
+                    seen.push(*field);
+                    pattern.push_str(&format!("(?P<{}>{})", field_name(*field), value));
+                }
+                // The marker renders as nothing for non-breaking changes.
+                if *field == Field::Breaking {
+                    pattern.push('?');
+                }
+            }
+            Part::Group(inner) => {
+                pattern.push_str("(?:");
+                let line_ended = header_pattern(inner, ticket_pattern, seen, pattern);
+                pattern.push_str(")?");
+                if line_ended {
+                    return true;
+                }
+            }
+        }
+    }
+    false
+}
+
+fn ticket_after(parts: &[Part], prefix: &str) -> bool {
+    parts.windows(2).any(|pair| match pair {
+        [Part::Literal(text), Part::Placeholder(Field::Ticket)] => {
+            text.contains('\n') && text.rsplit('\n').next() == Some(prefix)
+        }
+        _ => false,
+    }) || parts
+        .iter()
+        .any(|part| matches!(part, Part::Group(inner) if ticket_after(inner, prefix)))
+}
+
+fn field_name(field: Field) -> &'static str {
+    match field {
+        Field::Type => "type",
+        Field::Scope => "scope",
+        Field::Breaking => "breaking",
+        Field::Subject => "subject",
+        Field::Ticket => "ticket",
+        Field::Body => "body",
+        Field::Footers => "footers",
+    }
+}
+
+/// Git only reads trailers from the last paragraph, so a template that puts
+/// `Refs: {ticket}` in its own group must not split it from `{footers}`.
+/// Only paragraphs rendered by footer groups are joined; body text that
+/// happens to look like `Context: we need X` stays where it is.
+fn join_footer_paragraphs(message: &str, footer_ranges: &[Range<usize>]) -> String {
+    let offset = message.len() - message.trim_start().len();
+    let message = message.trim();
+    let mut paragraphs: Vec<(usize, String)> = Vec::new();
+    let mut start = 0;
+    for paragraph in message.split("\n\n") {
+        paragraphs.push((offset + start, paragraph.to_string()));
+        start += paragraph.len() + 2;
+    }
+
+    let from_footer_group = |start: usize| footer_ranges.iter().any(|range| range.contains(&start));
+    while paragraphs.len() > 2 {
+        let last = &paragraphs[paragraphs.len() - 1];
+        let previous = &paragraphs[paragraphs.len() - 2];
+        if !from_footer_group(last.0) || !from_footer_group(previous.0) {
+            break;
+        }
+        let (_, last) = paragraphs.pop().unwrap_or_default();
+        if let Some((_, previous)) = paragraphs.last_mut() {
+            previous.push('\n');
+            previous.push_str(&last);
+        }
+    }
+
+    paragraphs
+        .into_iter()
+        .map(|(_, paragraph)| paragraph)
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values() -> Values {
+        Values {
+            commit_type: "feat".to_string(),
+            subject: "add x".to_string(),
+            ticket: "ABC-1".to_string(),
+            ..Values::default()
+        }
+    }
+
+    #[test]
+    fn joins_the_ticket_footer_with_the_footers() {
+        let template =
+            Template::parse("{type}: {subject}[\n\n{body}][\n\nRefs: {ticket}][\n\n{footers}]")
+                .unwrap();
+        let values = Values {
+            body: "Some body.".to_string(),
+            footers: vec!["Co-authored-by: A <a@b>".to_string()],
+            ..values()
+        };
+
+        assert_eq!(
+            template.render(&values),
+            "feat: add x\n\nSome body.\n\nRefs: ABC-1\nCo-authored-by: A <a@b>"
+        );
+    }
+
+    #[test]
+    fn keeps_body_paragraphs_that_look_like_footers() {
+        let template = Template::parse("{type}: {subject}[\n\n{body}][\n\n{footers}]").unwrap();
+        let values = Values {
+            body: "Context: we need X".to_string(),
+            footers: vec!["Refs: ABC-1".to_string()],
+            ..values()
+        };
+
+        assert_eq!(
+            template.render(&values),
+            "feat: add x\n\nContext: we need X\n\nRefs: ABC-1"
+        );
+    }
+
+    #[test]
+    fn default_template_leaves_out_empty_groups() {
+        let template = Template::parse(DEFAULT_TEMPLATE).unwrap();
+        let full = Values {
+            scope: "api".to_string(),
+            breaking: true,
+            ..values()
+        };
+        assert_eq!(template.render(&full), "feat(api)!: add x (ABC-1)");
+
+        let bare = Values {
+            commit_type: String::new(),
+            ticket: String::new(),
+            ..values()
+        };
+        assert_eq!(template.render(&bare), "add x");
+    }
+
+    #[test]
+    fn parses_escapes() {
+        let template = Template::parse(r"\[{ticket}\] {subject}\n\nRefs: \{x\} \\").unwrap();
+        assert_eq!(template.render(&values()), "[ABC-1] add x\n\nRefs: {x} \\");
+    }
+
+    #[test]
+    fn rejects_malformed_templates() {
+        let error = |text: &str| Template::parse(text).err().unwrap();
+
+        assert_eq!(error("{nope}"), "unknown placeholder '{nope}'");
+        assert_eq!(error("[{subject}"), "unclosed '['");
+        assert_eq!(error("{subject}]"), "unmatched ']'");
+        assert_eq!(error("{subject}}"), "unmatched '}'");
+        assert_eq!(error(r"\x"), "unknown escape '\\x'");
+        assert_eq!(error("x\\"), "template ends with a lone '\\'");
+    }
+
+    #[test]
+    fn matches_rendered_headers() {
+        let template = Template::parse(DEFAULT_TEMPLATE).unwrap();
+        let values = template
+            .match_header("fix(ui)!: handle y (ABC-12)", "[A-Z]+-[0-9]+")
+            .unwrap();
+
+        assert_eq!(values.commit_type, "fix");
+        assert_eq!(values.scope, "ui");
+        assert!(values.breaking);
+        assert_eq!(values.subject, "handle y");
+        assert_eq!(values.ticket, "ABC-12");
+        assert!(Template::parse("Refs: {ticket}\n\n{subject}")
+            .unwrap()
+            .match_header("not it", "[A-Z]+-[0-9]+")
+            .is_none());
+    }
+
+    #[test]
+    fn finds_ticket_footers() {
+        let template = Template::parse("{subject}[\n\nRefs: {ticket}]").unwrap();
+        assert!(template.has_ticket_footer("Refs: "));
+        assert!(!Template::parse(DEFAULT_TEMPLATE)
+            .unwrap()
+            .has_ticket_footer("Refs: "));
+    }
+}
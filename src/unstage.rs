@@ -0,0 +1,28 @@
+use crate::git_operations;
+use inquire::MultiSelect;
+
+pub fn unstage_files() -> Result<(), String> {
+    let repo = git_operations::get_repository().map_err(|e| e.to_string())?;
+
+    let (_changes, staged) = git_operations::get_changes(&repo);
+
+    if staged.is_empty() {
+        println!("No staged files found.");
+        return Ok(());
+    }
+
+    let selected_staged = MultiSelect::new("Select changes to unstage:", staged)
+        .prompt()
+        .map_err(|e| format!("An error occurred during selection: {}", e))?;
+
+    if selected_staged.is_empty() {
+        println!("No files selected.");
+        return Ok(());
+    }
+
+    git_operations::reset_files(&repo, selected_staged)
+        .map_err(|e| format!("Failed to unstage files: {}", e))?;
+
+    println!("✅ Unstaged files successfuly!");
+    Ok(())
+}